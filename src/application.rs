@@ -1,52 +1,167 @@
 extern crate glfw;
 
-use crate::text::TextManager;
-use crate::uniform::*;
+use crate::text::{SdfStyle, TextManager, TextMesh};
+use crate::text_layout::{TextAlign, TextLayout};
 
 use glam::*;
-use glfw::ffi::{GLFWwindow, glfwSetInputMode, CURSOR, CURSOR_DISABLED, glfwGetCursorPos};
-use glfw::{Action, Context, Glfw, Key, Window, WindowEvent};
+use glfw::ffi::{glfwSetInputMode, CURSOR, CURSOR_DISABLED, glfwGetCursorPos};
+use glfw::{Context, Glfw, Window, WindowEvent};
 
 
+use std::path::Path;
+use std::rc::Rc;
 use std::sync::mpsc::Receiver;
 
+use crate::camera::Camera;
+use crate::capture::{self, FrameRecorder};
+use crate::framebuffer::{self, ColorFormat, DepthAttachment, FramebufferDescriptor};
 use crate::gl::{self};
+use crate::gamepad::Gamepads;
+use crate::hud::{DebugHud, HudView};
+use crate::input::InputMap;
+use crate::scene_file;
+use crate::scene_graph::{NodeId, SceneGraph, Transform};
+use crate::light::Light;
+use crate::postprocess::{PostEffect, PostProcessChain};
+use crate::shadow::{ShadowMap, ShadowSettings};
+use crate::stats;
+use crate::timestep::FixedTimestep;
+use crate::uniform_block::{
+    CameraBlock, LightsBlock, UniformBuffer, CAMERA_BINDING_POINT, LIGHTS_BINDING_POINT, MAX_LIGHTS,
+};
 use crate::vertex::{Model};
-use crate::Program;
 
 extern crate freetype;
 
-extern "C" fn framebuffer_size_callback(_window: *mut GLFWwindow, width: i32, height: i32) {
-    unsafe {
-        gl::Viewport(0, 0, width, height);
-    }
-}
-
 pub const VERTEX_SHADER: gl::types::GLenum = gl::VERTEX_SHADER;
 pub const FRAGMENT_SHADER: gl::types::GLenum = gl::FRAGMENT_SHADER;
 
 pub struct Application {
-    models: Vec<Model>,
+    scene: SceneGraph,
     glfw: Glfw,
     window: Window,
     events: Receiver<(f64, WindowEvent)>,
     text_manager: Option<TextManager>,
+    // Made with the text manager, the text doesn't change
+    greeting: Option<TextMesh>,
+    camera_buffer: UniformBuffer<CameraBlock>,
+    lights: Vec<Light>,
+    lights_buffer: UniformBuffer<LightsBlock>,
+    shadow_map: Option<ShadowMap>,
+    // Only the scene's meshes are drawn as lines, shadows, post effects and text stay filled
+    wireframe: bool,
+    post_process: Option<PostProcessChain>,
+    recorder: Option<FrameRecorder>,
+    input: InputMap,
+    gamepads: Gamepads,
+    hud: DebugHud,
+}
+
+// What a frame is drawn from
+#[derive(Clone, Copy)]
+pub struct FrameView {
+    pub projection: Mat4,
+    pub look_at: Mat4,
+    pub camera_position: Vec3,
+}
+
+impl FrameView {
+    pub fn new(projection: Mat4, look_at: Mat4, camera_position: Vec3) -> FrameView {
+        FrameView {
+            projection: projection,
+            look_at: look_at,
+            camera_position: camera_position,
+        }
+    }
+
+    pub fn from_camera(camera: &Camera) -> FrameView {
+        FrameView::new(camera.projection_matrix(), camera.view_matrix(), camera.eye())
+    }
+
+    // Perspective view from camera_position towards target, fov in degrees
+    pub fn looking_at(camera_position: Vec3, target: Vec3, fov: f32, aspect_ratio: f32) -> FrameView {
+        FrameView::new(
+            Mat4::perspective_rh_gl(fov.to_radians(), aspect_ratio, 0.1, 100.0),
+            Mat4::look_at_rh(camera_position, target, Vec3::Y),
+            camera_position,
+        )
+    }
+}
+
+// Simulation rate of the render loop, see FixedTimestep
+const UPDATES_PER_SECOND: f64 = 120.0;
+
+// Per second, what the old per frame steps gave at 60 frames per second
+const MOVE_SPEED: f32 = 1.2;
+const STRAFE_SPEED: f32 = 0.54;
+const ROTATION_SPEED: f32 = std::f32::consts::PI * 0.6;
+// Degrees of yaw and pitch, for the gamepad
+const LOOK_SPEED: f32 = 120.0;
+// Degrees of field of view
+const ZOOM_SPEED: f32 = 12.0;
+const MIXVALUE_SPEED: f32 = 1.2;
+
+// What the render loop moves around besides the scene graph and the lights
+struct LoopState {
+    cube_node: Option<NodeId>,
+    mixvalue: f32,
+    camera: Camera,
+    // From before the last update, render() blends them with the current ones.
+    // Changes to the camera outside update() are made to both.
+    previous_camera: Camera,
+    previous_cube_transform: Option<Transform>,
+    // Whose transform the debug HUD shows
+    selected: Option<NodeId>,
 }
 
 impl Application {
-    pub fn new() -> Application {
-        let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+    pub fn new() -> Result<Application, String> {
+        Application::create(1024, 768, true)
+    }
+
+    /*
+     * Same as new() but the window is never shown, frames are drawn with
+     * render_offscreen(). GLFW still needs a display, on machines without
+     * a GPU run under Xvfb (xvfb-run) and Mesa's llvmpipe does the rendering.
+     */
+    pub fn new_headless(
+        width: gl::types::GLsizei,
+        height: gl::types::GLsizei,
+    ) -> Result<Application, String> {
+        Application::create(width, height, false)
+    }
+
+    fn create(
+        width: gl::types::GLsizei,
+        height: gl::types::GLsizei,
+        visible: bool,
+    ) -> Result<Application, String> {
+        let mut glfw = match glfw::init(glfw::LOG_ERRORS) {
+            Ok(glfw) => glfw,
+            Err(e) => return Err(format!("Failed to initialize GLFW: {:?}", e)),
+        };
 
         glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
         glfw.window_hint(glfw::WindowHint::OpenGlProfile(
             glfw::OpenGlProfileHint::Core,
         ));
-
-        let (mut window, events) = glfw
-            .create_window(1024, 768, "rust-opengl", glfw::WindowMode::Windowed)
-            .expect("Failed to create GLFW window.");
+        glfw.window_hint(glfw::WindowHint::Visible(visible));
+
+        let (mut window, events) = match glfw.create_window(
+            width as u32,
+            height as u32,
+            "rust-opengl",
+            glfw::WindowMode::Windowed,
+        ) {
+            Some(created) => created,
+            None => return Err("Failed to create GLFW window".to_string()),
+        };
 
         window.set_key_polling(true);
+        window.set_mouse_button_polling(true);
+        // Both end up in handle_resize()
+        window.set_framebuffer_size_polling(true);
+        window.set_content_scale_polling(true);
         window.make_current();
 
         // the supplied function must be of the type:
@@ -57,228 +172,179 @@ impl Application {
         // loading a specific function pointer
         gl::Viewport::load_with(|s| window.get_proc_address(s) as *const _);
 
+        // On HiDPI screens the framebuffer can be bigger than the window
+        let (framebuffer_width, framebuffer_height) = window.get_framebuffer_size();
+
         unsafe {
-            gl::Viewport(0, 0, 1024, 768);
+            gl::Viewport(0, 0, framebuffer_width, framebuffer_height);
             gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+            gl::Enable(gl::DEPTH_TEST);
         }
 
-        unsafe {
-            glfw::ffi::glfwSetFramebufferSizeCallback(
-                window.window_ptr(),
-                Some(framebuffer_size_callback),
-            );
-        }
+        let gamepads = Gamepads::new(&glfw);
 
-        Application {
-            models: Vec::new(),
+        Ok(Application {
+            scene: SceneGraph::new(),
             glfw: glfw,
             window: window,
             events: events,
             text_manager: None,
-        }
+            greeting: None,
+            camera_buffer: UniformBuffer::new(CAMERA_BINDING_POINT),
+            lights: Vec::new(),
+            lights_buffer: UniformBuffer::new(LIGHTS_BINDING_POINT),
+            shadow_map: None,
+            wireframe: false,
+            post_process: None,
+            recorder: None,
+            input: InputMap::new(),
+            gamepads: gamepads,
+            hud: DebugHud::new(),
+        })
     }
 
     pub fn attach_text_manager(&mut self, text_manager: TextManager) {
         self.text_manager = Some(text_manager);
+        self.greeting = Some(TextMesh::new());
+        self.update_text_projection();
     }
 
-    pub fn render_models(&mut self) {
-        let mut cur_off_x: f32 = 0.0;
-        let mut cur_off_y: f32 = 0.0;
-        let mut cur_off_z: f32 = -0.4;
-        let camera_cur_off_x: f32 = 0.0;
-        let camera_cur_off_y: f32 = 0.2;
-        let camera_cur_off_z: f32 = 2.0;
-        let mut mixvalue: f32 = 0.5;
-        let mut moving_up: bool = false;
-        let mut moving_down: bool = false;
-        let mut moving_left: bool = false;
-        let mut moving_right: bool = false;
-        let mut moving_in: bool = false;
-        let mut moving_out: bool = false;
-        let mut camera_moving_up: bool = false;
-        let mut camera_moving_down: bool = false;
-        let mut camera_moving_left: bool = false;
-        let mut camera_moving_right: bool = false;
-        let mut camera_moving_forwards: bool = false;
-        let mut camera_moving_backwards: bool = false;
-        let mut x_angle_multiplier: f32 = 0.0;
-        let mut y_angle_multiplier: f32 = 0.0;
-        let mut z_angle_multiplier: f32 = 0.0;
-        let mut x_rot_cwise = false;
-        let mut x_rot_ccwise = false;
-        let mut y_rot_cwise = false;
-        let mut y_rot_ccwise = false;
-        let mut z_rot_cwise = false;
-        let mut z_rot_ccwise = false;
-        let mut reset_all_angles = false;
-        let mut yaw: f32 = -90.0;
-        let mut pitch: f32 = 0.0;
-        let mut zoom_in = false;
-        let mut zoom_out = false;
-        let mut reset_zoom = false;
-        let mut fov_val = 45.0;
-
-        let mut mixvalue_grow = false;
-        let mut mixvalue_shrink = false;
+    // Width over height of the window's framebuffer
+    pub fn aspect_ratio(&self) -> f32 {
+        let (width, height) = self.window.get_framebuffer_size();
+        width.max(1) as f32 / height.max(1) as f32
+    }
 
-        let mut last_cursor_x: f64 = 400.0;
-        let mut last_cursor_y: f64 = 300.0;
-        let mut current_cursor_x: f64 = 0.0;
-        let mut current_cursor_y: f64 = 0.0;
+    /*
+     * Brings everything sized after the window up to date, for framebuffer
+     * size and content scale changes. A minimized window has a 0x0
+     * framebuffer, everything keeps its last size then.
+     */
+    pub fn handle_resize(&mut self) {
+        let (width, height) = self.window.get_framebuffer_size();
+        if width <= 0 || height <= 0 {
+            return;
+        }
 
         unsafe {
-            gl::Enable(gl::DEPTH_TEST);
+            gl::Viewport(0, 0, width, height);
         }
 
-        unsafe {
-            glfwSetInputMode(self.window.window_ptr(), CURSOR, CURSOR_DISABLED);
+        if let Some(post_process) = self.post_process.as_mut() {
+            if let Err(e) = post_process.resize(width, height) {
+                eprintln!("ERROR: {}", e);
+            }
         }
 
-        self.models[0].use_program();
+        self.update_text_projection();
+    }
 
-        let mut perspective_projection_matrix =
-            Mat4::perspective_rh_gl(f32::to_radians(fov_val), 1024.0 / 768.0, 0.1, 100.0);
+    // Text is placed in screen coordinates rather than framebuffer pixels,
+    // so it's the same size on HiDPI screens as on any other
+    fn update_text_projection(&mut self) {
+        let (width, height) = self.window.get_framebuffer_size();
+        let (x_scale, y_scale) = self.window.get_content_scale();
 
-        self.models[0].meshes[0].uniforms[5].update(UniformPackedParam::UniformMatrix4FV(
-            Uniform4FVMatrix(perspective_projection_matrix),
-        ));
+        if let Some(text_manager) = self.text_manager.as_mut() {
+            text_manager.set_screen_size(
+                width.max(1) as f32 / x_scale.max(0.1),
+                height.max(1) as f32 / y_scale.max(0.1),
+            );
+        }
+    }
 
-        // Initial position
-        let mut camera_position = Vec3::new(camera_cur_off_x, camera_cur_off_y, camera_cur_off_z);
+    pub fn render_models(&mut self) {
+        let camera = Camera::new(Vec3::new(0.0, 0.2, 2.0), -90.0, 0.0, self.aspect_ratio());
+        let mut state = LoopState {
+            // The arrow/rotation keys move the node named "cube", if the scene has one
+            cube_node: self.scene.find("cube"),
+            mixvalue: 0.5,
+            camera: camera,
+            previous_camera: camera,
+            previous_cube_transform: None,
+            selected: self.scene.find("cube"),
+        };
 
-        while !self.window.should_close() {
-            unsafe {
-                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-            }
-            for (_, event) in glfw::flush_messages(&self.events) {
-                handle_window_event(
-                    &mut self.window,
-                    event,
-                    &mut moving_up,
-                    &mut moving_down,
-                    &mut moving_left,
-                    &mut moving_right,
-                    &mut moving_in,
-                    &mut moving_out,
-                    &mut x_rot_cwise,
-                    &mut x_rot_ccwise,
-                    &mut y_rot_cwise,
-                    &mut y_rot_ccwise,
-                    &mut z_rot_cwise,
-                    &mut z_rot_ccwise,
-                    &mut reset_all_angles,
-                    &mut mixvalue_grow,
-                    &mut mixvalue_shrink,
-                    &mut camera_moving_forwards,
-                    &mut camera_moving_backwards,
-                    &mut camera_moving_down,
-                    &mut camera_moving_up,
-                    &mut camera_moving_left,
-                    &mut camera_moving_right,
-                    &mut zoom_in,
-                    &mut zoom_out,
-                    &mut reset_zoom,
-                );
-            }
+        let mut last_cursor_x: f64 = 400.0;
+        let mut last_cursor_y: f64 = 300.0;
+        let mut current_cursor_x: f64 = 0.0;
+        let mut current_cursor_y: f64 = 0.0;
 
-            if moving_in == true {
-                cur_off_z += 0.02;
-            }
-            if moving_out == true {
-                cur_off_z -= 0.02;
-            }
-            if moving_down == true {
-                cur_off_y -= 0.02;
-            }
-            if moving_up == true {
-                cur_off_y += 0.02;
-            }
-            if moving_left == true {
-                cur_off_x -= 0.02;
-            }
-            if moving_right == true {
-                cur_off_x += 0.02;
-            }
+        unsafe {
+            glfwSetInputMode(self.window.window_ptr(), CURSOR, CURSOR_DISABLED);
+        }
 
-            if x_rot_ccwise == true {
-                x_angle_multiplier += 0.01;
-            }
-            if x_rot_cwise == true {
-                x_angle_multiplier -= 0.01;
-            }
+        let mut timestep = FixedTimestep::new(UPDATES_PER_SECOND);
+        let mut last_time = self.glfw.get_time();
 
-            if y_rot_ccwise == true {
-                y_angle_multiplier += 0.01;
-            }
-            if y_rot_cwise == true {
-                y_angle_multiplier -= 0.01;
+        while !self.window.should_close() {
+            self.input.begin_frame();
+            // Collected first, resizing needs all of self
+            let events: Vec<WindowEvent> = glfw::flush_messages(&self.events)
+                .map(|(_, event)| event)
+                .collect();
+            for event in events {
+                match event {
+                    WindowEvent::FramebufferSize(..) | WindowEvent::ContentScale(..) => {
+                        self.handle_resize();
+                        state.camera.aspect_ratio = self.aspect_ratio();
+                        state.previous_camera.aspect_ratio = state.camera.aspect_ratio;
+                    }
+                    _ => self.input.handle_event(&event),
+                }
             }
+            self.gamepads.poll(&self.glfw, &mut self.input);
 
-            if z_rot_ccwise == true {
-                z_angle_multiplier += 0.01;
-            }
-            if z_rot_cwise == true {
-                z_angle_multiplier -= 0.01;
+            if self.input.pressed("quit") {
+                self.window.set_should_close(true);
             }
 
-            if reset_all_angles == true {
-                x_angle_multiplier = 0.0;
-                y_angle_multiplier = 0.0;
-                z_angle_multiplier = 0.0
+            if self.input.pressed("toggle_wireframe") {
+                self.wireframe = !self.wireframe;
             }
 
-            if zoom_in == true {
-                if fov_val > 0.0 {
-                    fov_val -= 0.2;
+            if self.input.pressed("toggle_grayscale") {
+                let toggled = if self.remove_post_effect("grayscale") {
+                    Ok(())
+                } else {
+                    self.add_post_effect("grayscale", PostEffect::Grayscale)
+                };
+                if let Err(e) = toggled {
+                    eprintln!("ERROR: {}", e);
                 }
-            } 
+            }
 
-            if zoom_out == true {
-                if fov_val < 360.0 {
-                    fov_val += 0.2;
+            if self.input.pressed("toggle_recording") {
+                if self.recorder.is_some() {
+                    self.stop_recording();
+                } else if let Err(e) = self.start_recording(&capture::recording_directory(), 60.0) {
+                    eprintln!("ERROR: {}", e);
                 }
             }
 
-            if reset_zoom == true {
-                fov_val = 45.0;
+            if self.input.pressed("toggle_hud") {
+                self.hud.toggle();
             }
 
-           
-            if zoom_out == true || zoom_in == true || reset_zoom == true {
-                perspective_projection_matrix =
-                    Mat4::perspective_rh_gl(f32::to_radians(fov_val), 1024.0 / 768.0, 0.1, 100.0);
-
-                #[cfg(feature = "printdebugs")]
-                println!("zoom_in/out_perspective: {:?}", perspective_projection_matrix);
-
-                self.models[0].use_program();
-                for mesh in self.models[0].meshes.iter_mut() {
-                    mesh.uniforms[5].update(UniformPackedParam::UniformMatrix4FV(
-                        Uniform4FVMatrix(perspective_projection_matrix),
-                    ));
-                }
+            // Walks through the nodes in the order the scene file has them
+            if self.input.pressed("select_next") {
+                let count = self.scene.iter().len();
+                state.selected = match state.selected {
+                    _ if count == 0 => None,
+                    Some(id) => Some((id + 1) % count),
+                    None => Some(0),
+                };
             }
 
-            let rotate_about_x_matrix =
-                Mat4::from_rotation_x(std::f32::consts::PI * x_angle_multiplier);
-            let rotate_about_y_matrix =
-                Mat4::from_rotation_y(std::f32::consts::PI * y_angle_multiplier);
-            let rotate_about_z_matrix =
-                Mat4::from_rotation_z(std::f32::consts::PI * z_angle_multiplier);
-            let translation_matrix =
-                Mat4::from_translation(Vec3::new(cur_off_x, cur_off_y, cur_off_z));
-
-            self.models[0].meshes[0].textures[0].set_active_texture(0);
-            self.models[0].meshes[0].textures[1].set_active_texture(1);
-
-            if mixvalue_grow == true {
-                mixvalue += 0.02;
-            }
-            if mixvalue_shrink == true {
-                mixvalue -= 0.02;
+            if self.input.pressed("cycle_camera_mode") {
+                let mode = state.camera.mode().next();
+                state.camera.set_mode(mode);
+                // Nothing to blend between two modes
+                state.previous_camera = state.camera;
+                println!("Camera mode: {:?}", mode);
             }
 
+            // Mouse look follows the cursor as far as it moved, however long that took
             unsafe {
                 glfwGetCursorPos(self.window.window_ptr(), &mut current_cursor_x as *mut f64, &mut current_cursor_y as *mut f64);
             }
@@ -287,347 +353,536 @@ impl Application {
             let cursor_y_diff = last_cursor_y - current_cursor_y;
             last_cursor_y = current_cursor_y;
 
-            yaw -= 0.03 * cursor_x_diff as f32;
-            pitch += 0.03 * cursor_y_diff as f32;
-            if pitch < -89.95 {
-                pitch = -89.95;
+            // Turned at once, not blended in over the next update
+            for camera in [&mut state.camera, &mut state.previous_camera] {
+                camera.rotate(-0.03 * cursor_x_diff as f32, 0.03 * cursor_y_diff as f32);
             }
 
-            if pitch > 89.95 {
-                pitch = 89.95;
+            // A recording advances by exactly one recorded frame each frame,
+            // however long writing the PNGs takes
+            let now = self.glfw.get_time();
+            self.hud.record_frame((now - last_time) as f32);
+            let frame_time = match self.recorder.as_ref() {
+                Some(recorder) => recorder.timestep() as f64,
+                None => now - last_time,
+            };
+            last_time = now;
+
+            for _ in 0..timestep.advance(frame_time) {
+                self.update(&mut state, timestep.step());
+            }
+
+            self.render(&state, timestep.alpha());
+
+            // Captures read the back buffer, so before it's swapped
+            if self.input.pressed("screenshot") {
+                let path = capture::screenshot_path();
+                match self.capture_frame(&path) {
+                    Ok(()) => println!("Saved screenshot to {}", path.display()),
+                    Err(e) => eprintln!("ERROR: {}", e),
+                }
             }
 
-            #[cfg(feature = "printdebugs")]
-            println!("cur_x: {} cur_y: {} last_x: {} last_y: {} diff_x: {} diff_y: {}",
-                    current_cursor_x, current_cursor_y, last_cursor_x, last_cursor_y, cursor_x_diff,
-                    cursor_y_diff);
-            #[cfg(feature = "printdebugs")]
-            println!("yaw: {} pitch: {}", yaw, pitch);
+            if let Some(recorder) = self.recorder.as_mut() {
+                let path = recorder.next_frame_path();
+                if let Err(e) = self.capture_frame(&path) {
+                    eprintln!("ERROR: {}, stopping the recording", e);
+                    self.stop_recording();
+                }
+            }
 
-            // Gram-Schmidt process
-            // Positive Z axis leads outside the screen
+            self.window.swap_buffers();
+            self.glfw.poll_events();
+        }
+    }
 
-            let mut direction = Vec3::new(0.0,0.0,0.0);
-            direction.x = yaw.to_radians().cos() * pitch.to_radians().cos();
-            direction.y = pitch.to_radians().sin();
-            direction.z = yaw.to_radians().sin() * pitch.to_radians().cos();
+    // Moves everything the held keys move by dt seconds worth
+    fn update(&mut self, state: &mut LoopState, dt: f32) {
+        let input = &self.input;
+        let move_step = MOVE_SPEED * dt;
 
-            let camera_front = direction.normalize();
+        state.previous_camera = state.camera;
 
-            if camera_moving_forwards == true {
-                camera_position += camera_front*0.02;
-            }
+        if let Some(cube_id) = state.cube_node {
+            let cube_transform = &mut self.scene.node_mut(cube_id).transform;
+            state.previous_cube_transform = Some(*cube_transform);
 
-            if camera_moving_backwards == true {
-                camera_position -= camera_front*0.02;
+            if input.held("cube_in") {
+                cube_transform.translation.z += move_step;
+            }
+            if input.held("cube_out") {
+                cube_transform.translation.z -= move_step;
+            }
+            if input.held("cube_down") {
+                cube_transform.translation.y -= move_step;
+            }
+            if input.held("cube_up") {
+                cube_transform.translation.y += move_step;
+            }
+            if input.held("cube_left") {
+                cube_transform.translation.x -= move_step;
+            }
+            if input.held("cube_right") {
+                cube_transform.translation.x += move_step;
             }
 
-            camera_position.y = 0.2;
+            let rotation_step = ROTATION_SPEED * dt;
 
-            let camera_target = camera_position + camera_front;
-            // For the view matrix's coordinate system we want its z-axis
-            // to be positive and because by convention (in OpenLG)
-            // the camera points towards the neg z-axis we want to negate
-            // the direciton vector.
-            // the name "direction vector" is a misnomer, since it is actually
-            // pointing in the reverse direction of what it is targeting
-            let camera_direction = (camera_position - camera_target).normalize();
-            //let camera_direction = direction;
-            // To get the right-axis do a cross product between up and target
-            let c_up = Vec3::new(0.0, 1.0, 0.0);
-            let camera_right = c_up.cross(camera_direction).normalize();
-            // get up axis by crossing camera direction with camera right
-            let camera_up = camera_direction.cross(camera_right);
+            if input.held("rotate_x_ccw") {
+                cube_transform.rotation *= Quat::from_rotation_x(rotation_step);
+            }
+            if input.held("rotate_x_cw") {
+                cube_transform.rotation *= Quat::from_rotation_x(-rotation_step);
+            }
 
-            if camera_moving_left == true {
-                camera_position -= camera_right * 0.009;
+            if input.held("rotate_y_ccw") {
+                cube_transform.rotation *= Quat::from_rotation_y(rotation_step);
+            }
+            if input.held("rotate_y_cw") {
+                cube_transform.rotation *= Quat::from_rotation_y(-rotation_step);
             }
 
-            if camera_moving_right == true {
-                camera_position += camera_right * 0.009;
+            if input.held("rotate_z_ccw") {
+                cube_transform.rotation *= Quat::from_rotation_z(rotation_step);
+            }
+            if input.held("rotate_z_cw") {
+                cube_transform.rotation *= Quat::from_rotation_z(-rotation_step);
             }
 
-            if camera_moving_down == true {
-                camera_position.y -= 0.02;
-                if camera_position.y < 0.2 {
-                    camera_position.y = 0.2;
-                }
+            if input.held("reset_rotation") {
+                cube_transform.rotation = Quat::IDENTITY;
             }
+        }
 
-            if camera_moving_up == true {
-                camera_position.y += 0.02;
+        // The keypad moves the first light that has a position
+        if let Some(light_position) = self
+            .lights
+            .iter_mut()
+            .find_map(|light| light.position_mut())
+        {
+            if input.held("light_forward") {
+                light_position.z -= move_step;
+            }
+            if input.held("light_backward") {
+                light_position.z += move_step;
+            }
+            if input.held("light_left") {
+                light_position.x -= move_step;
+            }
+            if input.held("light_right") {
+                light_position.x += move_step;
             }
+            if input.held("light_down") {
+                light_position.y -= move_step;
+            }
+            if input.held("light_up") {
+                light_position.y += move_step;
+            }
+        }
 
+        state.camera.zoom((input.value("zoom_out") - input.value("zoom_in")) * ZOOM_SPEED * dt);
 
-            #[cfg(feature = "printdebugs")]
-            println!("camera_position: {:?}", camera_position);
+        if input.held("reset_zoom") {
+            state.camera.reset_zoom();
+        }
 
+        if input.held("mix_more") {
+            state.mixvalue += MIXVALUE_SPEED * dt;
+        }
+        if input.held("mix_less") {
+            state.mixvalue -= MIXVALUE_SPEED * dt;
+        }
 
-            // From these 3 vectors we can create a LookAt matrix
-            let mut mat_A = Mat4::from_cols(
-                                        Vec4::from((camera_right, 0.0)),
-                                        Vec4::from((camera_up, 0.0)),
-                                        Vec4::from((camera_direction,0.0)),
-                                        Vec4::W
-            );
-            mat_A = mat_A.transpose();
+        // What these mean depends on the camera mode, see Camera::move_by
+        let axis = |positive: &str, negative: &str| input.value(positive) - input.value(negative);
+        // The mouse turns the camera in render_models(), sticks turn it here
+        state.camera.rotate(
+            axis("look_right", "look_left") * LOOK_SPEED * dt,
+            axis("look_up", "look_down") * LOOK_SPEED * dt,
+        );
 
-            let mat_B = Mat4::from_cols(
-                                    Vec4::X,
-                                    Vec4::Y,
-                                    Vec4::Z,
-                                    Vec4::from((-camera_position, 1.0))
+        state.camera.move_by(
+            axis("move_forward", "move_backward") * move_step,
+            axis("move_right", "move_left") * STRAFE_SPEED * dt,
+            axis("move_up", "move_down") * move_step,
+        );
+    }
 
-            );
+    // Draws the state alpha of the way from the previous update to the last one
+    fn render(&mut self, state: &LoopState, alpha: f32) {
+        let camera = state.camera.interpolate(&state.previous_camera, alpha);
 
-            let LookAt = mat_A * mat_B;
-
-            // Cube mesh
-            self.models[0].use_program();
-            for mesh in self.models[0].meshes.iter_mut() {
-
-
-                mesh.uniforms[0].update(UniformPackedParam::UniformMatrix4FV(
-                    Uniform4FVMatrix(rotate_about_x_matrix),
-                ));
-                mesh.uniforms[1].update(UniformPackedParam::UniformMatrix4FV(
-                    Uniform4FVMatrix(rotate_about_y_matrix),
-                ));
-                mesh.uniforms[2].update(UniformPackedParam::UniformMatrix4FV(
-                    Uniform4FVMatrix(rotate_about_z_matrix),
-                ));
-                mesh.uniforms[3].update(UniformPackedParam::UniformMatrix4FV(
-                    Uniform4FVMatrix(translation_matrix),
-                ));
-                mesh.uniforms[4].update(UniformPackedParam::Uniform1F(
-                    Uniform1FParam(mixvalue)));
-
-                mesh.uniforms[6].update(UniformPackedParam::UniformMatrix4FV(
-                    Uniform4FVMatrix(LookAt)
-                ));
-                
-                mesh.bind_vao();
-                mesh.render();
+        let mut current_cube_transform = None;
+
+        if let Some(cube_id) = state.cube_node {
+            let cube = self.scene.node_mut(cube_id);
+
+            // Drawn in between, put back after the frame
+            if let Some(previous) = state.previous_cube_transform {
+                current_cube_transform = Some(cube.transform);
+                cube.transform.translation = previous.translation.lerp(cube.transform.translation, alpha);
+                cube.transform.rotation = previous.rotation.slerp(cube.transform.rotation, alpha);
             }
 
-            for model in self.models[1..].iter_mut() {
+            // Only the cube's program has a mixvalue, it's set while that program is in use
+            if let Some(model) = cube.model.as_mut() {
                 model.use_program();
                 for mesh in model.meshes.iter_mut() {
-                    mesh.bind_vao();
-                    mesh.textures[0].set_active_texture(0);
-                    mesh.uniforms[0].update(UniformPackedParam::UniformMatrix4FV(
-                        Uniform4FVMatrix(perspective_projection_matrix),
-                    ));
-
-                    mesh.uniforms[1].update(UniformPackedParam::UniformMatrix4FV(
-                        Uniform4FVMatrix(LookAt)
-                    ));
-
+                    mesh.set_uniform("mixvalue", state.mixvalue);
                 }
-                model.render();
             }
+        }
 
-            self.text_manager.as_ref().unwrap().use_text_program();
-            self.text_manager.as_mut().unwrap().render_text("Greetings mortals".to_string(), 25.0, 25.0, 1.0, Vec3::new(0.5, 0.8, 0.2));
+        // Only this frame's draw calls are counted
+        stats::take_draw_calls();
 
-            self.window.swap_buffers();
-            self.glfw.poll_events();
-        }
-    }
+        self.render_frame(
+            &FrameView::from_camera(&camera),
+            None,
+        );
 
-    pub fn add_model(&mut self, model: Model) {
-        self.models.push(model);
-    }
-}
+        let draw_calls = stats::take_draw_calls();
 
-fn handle_window_event(
-    window: &mut glfw::Window,
-    event: glfw::WindowEvent,
-    moving_up: &mut bool,
-    moving_down: &mut bool,
-    moving_left: &mut bool,
-    moving_right: &mut bool,
-    moving_in: &mut bool,
-    moving_out: &mut bool,
-    x_rotate_cwise: &mut bool,
-    x_rotate_ccwise: &mut bool,
-    y_rotate_cwise: &mut bool,
-    y_rotate_ccwise: &mut bool,
-    z_rotate_cwise: &mut bool,
-    z_rotate_ccwise: &mut bool,
-    reset_all_angles: &mut bool,
-    mixvalue_grow: &mut bool,
-    mixvalue_shrink: &mut bool,
-    camera_moving_forwards: &mut bool,
-    camera_moving_backwards: &mut bool,
-    camera_moving_down: &mut bool,
-    camera_moving_up: &mut bool,
-    camera_moving_left: &mut bool,
-    camera_moving_right: &mut bool,
-    zoom_in: &mut bool,
-    zoom_out: &mut bool,
-    reset_zoom: &mut bool,
-) {
-    match event {
-        glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => window.set_should_close(true),
-        glfw::WindowEvent::Key(Key::C, _, Action::Press, _) => {
-            *moving_in = true;
-        }
-        glfw::WindowEvent::Key(Key::C, _, Action::Release, _) => {
-            *moving_in = false;
-        }
-        glfw::WindowEvent::Key(Key::W, _, Action::Press, _) => {
-            *camera_moving_forwards = true;
-        }
-        glfw::WindowEvent::Key(Key::W, _, Action::Release, _) => {
-            *camera_moving_forwards = false;
-        }
-        glfw::WindowEvent::Key(Key::S, _, Action::Press, _) => {
-            *camera_moving_backwards = true;
-        }
-        glfw::WindowEvent::Key(Key::S, _, Action::Release, _) => {
-            *camera_moving_backwards = false;
-        }
-        glfw::WindowEvent::Key(Key::A, _, Action::Press, _) => {
-            *camera_moving_left = true;
-        }
-        glfw::WindowEvent::Key(Key::A, _, Action::Release, _) => {
-            *camera_moving_left = false;
-        }
-        glfw::WindowEvent::Key(Key::D, _, Action::Press, _) => {
-            *camera_moving_right = true;
-        }
-        glfw::WindowEvent::Key(Key::D, _, Action::Release, _) => {
-            *camera_moving_right = false;
-        }
-        glfw::WindowEvent::Key(Key::Q, _, Action::Press, _) => {
-            *camera_moving_down = true;
-        }
-        glfw::WindowEvent::Key(Key::Q, _, Action::Release, _) => {
-            *camera_moving_down = false;
-        }
-        glfw::WindowEvent::Key(Key::E, _, Action::Press, _) => {
-            *camera_moving_up = true;
-        }
-        glfw::WindowEvent::Key(Key::E, _, Action::Release, _) => {
-            *camera_moving_up = false;
-        }
-        glfw::WindowEvent::Key(Key::Z, _, Action::Press, _) => {
-            *moving_out = true;
-        }
-        glfw::WindowEvent::Key(Key::Z, _, Action::Release, _) => {
-            *moving_out = false;
-        }
-        glfw::WindowEvent::Key(Key::Up, _, Action::Press, _) => {
-            *moving_up = true;
-        }
-        glfw::WindowEvent::Key(Key::Left, _, Action::Press, _) => {
-            *moving_left = true;
-        }
-        glfw::WindowEvent::Key(Key::Right, _, Action::Press, _) => {
-            *moving_right = true;
-        }
-        glfw::WindowEvent::Key(Key::Down, _, Action::Press, _) => {
-            *moving_down = true;
-        }
-        glfw::WindowEvent::Key(Key::Up, _, Action::Release, _) => {
-            *moving_up = false;
+        if let (Some(cube_id), Some(transform)) = (state.cube_node, current_cube_transform) {
+            self.scene.node_mut(cube_id).transform = transform;
         }
-        glfw::WindowEvent::Key(Key::Left, _, Action::Release, _) => {
-            *moving_left = false;
-        }
-        glfw::WindowEvent::Key(Key::Right, _, Action::Release, _) => {
-            *moving_right = false;
-        }
-        glfw::WindowEvent::Key(Key::Down, _, Action::Release, _) => {
-            *moving_down = false;
-        }
-        
-        glfw::WindowEvent::Key(Key::KpAdd, _, Action::Press, _) => {
-            *zoom_in = true;
+
+        // Over the finished frame, it's not part of the scene
+        if let Some(text_manager) = self.text_manager.as_mut() {
+            text_manager.use_text_program();
+            self.hud.render(
+                text_manager,
+                &HudView {
+                    camera: &camera,
+                    draw_calls: draw_calls,
+                    selected: state.selected.map(|id| self.scene.node(id)),
+                },
+            );
         }
-        glfw::WindowEvent::Key(Key::KpAdd, _, Action::Release, _) => {
-            *zoom_in = false;
+    }
+
+    // Node labels go into the scene like any other geometry, before post effects
+    fn render_labels(&mut self, view: &FrameView) {
+        let text_manager = match self.text_manager.as_mut() {
+            Some(text_manager) => text_manager,
+            None => return,
+        };
+        let font = match text_manager.default_font() {
+            Some(font) => font,
+            None => return,
+        };
+
+        let layout = TextLayout {
+            align: TextAlign::Center,
+            ..TextLayout::default()
+        };
+
+        // Outlined so they can be read against anything behind them
+        let overlay_style = text_manager.sdf_style();
+        text_manager.set_sdf_style(SdfStyle {
+            outline_color: Vec4::new(0.0, 0.0, 0.0, 0.9),
+            outline_width: 2.0,
+            ..SdfStyle::default()
+        });
+
+        for node in self.scene.iter() {
+            if let Some(label) = node.label.as_ref() {
+                let position = node.world_matrix().transform_point3(Vec3::ZERO) + label.offset;
+                text_manager.render_text_in_world(
+                    font,
+                    &label.text,
+                    position,
+                    label.height,
+                    &layout,
+                    label.color,
+                    view.look_at,
+                    view.projection,
+                );
+            }
         }
-        glfw::WindowEvent::Key(Key::KpSubtract, _, Action::Press, _) => {
-            *zoom_out = true;
+
+        text_manager.set_sdf_style(overlay_style);
+    }
+
+    // Draws the scene (and text) once into output, or the window when output is None
+    fn render_frame(&mut self, view: &FrameView, output: Option<&FramebufferDescriptor>) {
+        let (screen_width, screen_height) = self.window.get_framebuffer_size();
+
+        // Shared by every program through the Camera uniform block
+        self.camera_buffer.update(&CameraBlock::new(
+            view.projection,
+            view.look_at,
+            view.camera_position,
+        ));
+
+        self.lights_buffer.update(&LightsBlock::new(&self.lights));
+
+        self.scene.update_world_matrices();
+
+        if let Some(shadow_map) = self.shadow_map.as_mut() {
+            shadow_map.render(&self.lights, &self.scene);
         }
-        glfw::WindowEvent::Key(Key::KpSubtract, _, Action::Release, _) => {
-            *zoom_out = false;
+
+        // With post effects the scene is drawn offscreen first
+        match (self.post_process.as_ref(), output) {
+            (Some(post_process), _) => post_process.begin_scene(),
+            (None, Some(framebuffer)) => framebuffer.bind(),
+            (None, None) => unsafe {
+                gl::Viewport(0, 0, screen_width, screen_height);
+            },
         }
-        glfw::WindowEvent::Key(Key::KpMultiply, _, Action::Press, _) => {
-            *reset_zoom = true;
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            if self.wireframe {
+                gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+            }
         }
-        glfw::WindowEvent::Key(Key::KpMultiply, _, Action::Release, _) => {
-            *reset_zoom = false;
+
+        for node in self.scene.iter_mut() {
+            let world_matrix = node.world_matrix();
+            let model = match node.model.as_mut() {
+                Some(model) => model,
+                None => continue,
+            };
+
+            model.use_program();
+            let program = Rc::clone(model.program.as_ref().unwrap());
+
+            let mesh_transforms = model.mesh_transforms();
+
+            for (mesh, mesh_transform) in model.meshes.iter_mut().zip(mesh_transforms) {
+                mesh.bind_vao();
+                for (idx, texture) in mesh.textures.iter().enumerate() {
+                    texture.set_active_texture(idx as u32);
+                }
+
+                program.set_uniform("model", world_matrix * mesh_transform);
+                mesh.material.upload(&program);
+                match self.shadow_map.as_ref() {
+                    Some(shadow_map) => shadow_map.apply(&program),
+                    None => ShadowMap::apply_none(&program),
+                }
+
+                mesh.render();
+            }
         }
 
-        glfw::WindowEvent::Key(Key::T, _, Action::Press, _) => {
-            *reset_all_angles = true;
+        unsafe {
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
         }
-        glfw::WindowEvent::Key(Key::T, _, Action::Release, _) => {
-            *reset_all_angles = false;
+
+        self.render_labels(view);
+
+        if let Some(post_process) = self.post_process.as_ref() {
+            post_process.finish(output, screen_width, screen_height);
         }
 
-        glfw::WindowEvent::Key(Key::I, _, Action::Press, _) => {
-            *x_rotate_cwise = true;
+        if let (Some(text_manager), Some(greeting)) = (self.text_manager.as_mut(), self.greeting.as_mut()) {
+            text_manager.use_text_program();
+            if let Some(font) = text_manager.default_font() {
+                text_manager.update_text_mesh(
+                    greeting,
+                    font,
+                    "Greetings mortals",
+                    25.0,
+                    25.0,
+                    1.0,
+                    &TextLayout::default(),
+                );
+                text_manager.render_text_mesh(greeting, Vec3::new(0.5, 0.8, 0.2));
+            }
         }
-        glfw::WindowEvent::Key(Key::I, _, Action::Release, _) => {
-            *x_rotate_cwise = false;
+
+        FramebufferDescriptor::bind_default();
+    }
+
+    /*
+     * Renders frames frames of the scene into an offscreen target and returns
+     * the last one as RGBA8 rows, top row first. Works the same with a visible
+     * window, but is meant for Applications made with new_headless().
+     */
+    pub fn render_offscreen(
+        &mut self,
+        view: &FrameView,
+        frames: u32,
+        width: gl::types::GLsizei,
+        height: gl::types::GLsizei,
+    ) -> Result<Vec<u8>, String> {
+        let target = FramebufferDescriptor::new(
+            width,
+            height,
+            &[ColorFormat::Rgba8],
+            DepthAttachment::DepthStencilRenderbuffer,
+        )?;
+
+        if let Some(post_process) = self.post_process.as_mut() {
+            post_process.resize(width, height)?;
+        }
+        // One unit per pixel whatever the window's content scale, so
+        // the same view renders the same everywhere
+        if let Some(text_manager) = self.text_manager.as_mut() {
+            text_manager.set_screen_size(width as f32, height as f32);
+        }
+
+        for _ in 0..frames.max(1) {
+            self.render_frame(view, Some(&target));
         }
-        glfw::WindowEvent::Key(Key::U, _, Action::Press, _) => {
-            *x_rotate_ccwise = true;
+
+        unsafe {
+            gl::Finish();
         }
+        let pixels = target.read_pixels();
 
-        glfw::WindowEvent::Key(Key::U, _, Action::Release, _) => {
-            *x_rotate_ccwise = false;
+        // Back to the window's size for the next on screen frame
+        if let Some(post_process) = self.post_process.as_mut() {
+            let (screen_width, screen_height) = self.window.get_framebuffer_size();
+            post_process.resize(screen_width, screen_height)?;
         }
+        self.update_text_projection();
 
-        glfw::WindowEvent::Key(Key::K, _, Action::Press, _) => {
-            *y_rotate_cwise = true;
+        Ok(pixels)
+    }
+
+    // Writes what the window shows, the frame that's drawn but not yet swapped, as PNG
+    pub fn capture_frame(&self, path: &Path) -> Result<(), String> {
+        let (width, height) = self.window.get_framebuffer_size();
+
+        FramebufferDescriptor::bind_default();
+        unsafe {
+            gl::ReadBuffer(gl::BACK);
         }
-        glfw::WindowEvent::Key(Key::K, _, Action::Release, _) => {
-            *y_rotate_cwise = false;
+        let mut pixels = framebuffer::read_rgba_pixels(width, height);
+
+        // The window's alpha isn't meant to be seen, a PNG would show it
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = 255;
         }
-        glfw::WindowEvent::Key(Key::J, _, Action::Press, _) => {
-            *y_rotate_ccwise = true;
+
+        capture::write_png(path, width as u32, height as u32, &pixels)
+    }
+
+    // Every frame from now on gets written to directory, see FrameRecorder
+    pub fn start_recording(&mut self, directory: &Path, frames_per_second: f32) -> Result<(), String> {
+        self.recorder = Some(FrameRecorder::new(directory, frames_per_second)?);
+        println!("Recording frames to {}", directory.display());
+        Ok(())
+    }
+
+    // Returns how many frames were written
+    pub fn stop_recording(&mut self) -> u32 {
+        match self.recorder.take() {
+            Some(recorder) => {
+                println!(
+                    "Recorded {} frames to {}",
+                    recorder.frame_count(),
+                    recorder.directory().display()
+                );
+                recorder.frame_count()
+            }
+            None => 0,
         }
-        glfw::WindowEvent::Key(Key::J, _, Action::Release, _) => {
-            *y_rotate_ccwise = false;
+    }
+
+    // Rebind actions here, see InputMap
+    pub fn input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.input
+    }
+
+    pub fn set_input_map(&mut self, input: InputMap) {
+        self.input = input;
+    }
+
+    // Adds the model as a new root node of the scene graph
+    pub fn add_model(&mut self, model: Model) -> NodeId {
+        if let Err(e) = self.bind_uniform_blocks(&model) {
+            eprintln!("ERROR: {}", e);
         }
+        self.scene.add_node("", Transform::identity(), Some(model), None)
+    }
 
-        glfw::WindowEvent::Key(Key::M, _, Action::Press, _) => {
-            *z_rotate_cwise = true;
+    pub fn add_light(&mut self, light: Light) -> Result<usize, String> {
+        if self.lights.len() == MAX_LIGHTS {
+            return Err(format!("Can't have more than {} lights", MAX_LIGHTS));
         }
-        glfw::WindowEvent::Key(Key::M, _, Action::Release, _) => {
-            *z_rotate_cwise = false;
+
+        self.lights.push(light);
+        Ok(self.lights.len() - 1)
+    }
+
+    // Shadows come from the first directional or spot light with cast_shadows set
+    pub fn enable_shadows(&mut self, settings: ShadowSettings) -> Result<(), String> {
+        self.shadow_map = Some(ShadowMap::new(settings)?);
+        Ok(())
+    }
+
+    pub fn disable_shadows(&mut self) {
+        self.shadow_map = None;
+    }
+
+    // Effects run in the order they were added, the offscreen
+    // targets are created with the first one
+    pub fn add_post_effect(&mut self, name: &str, effect: PostEffect) -> Result<(), String> {
+        if self.post_process.is_none() {
+            let (width, height) = self.window.get_framebuffer_size();
+            self.post_process = Some(PostProcessChain::new(width, height)?);
         }
-        glfw::WindowEvent::Key(Key::N, _, Action::Press, _) => {
-            *z_rotate_ccwise = true;
+
+        self.post_process.as_mut().unwrap().add_effect(name, effect)
+    }
+
+    // Drawing goes straight to the window again once the last effect is gone
+    pub fn remove_post_effect(&mut self, name: &str) -> bool {
+        let post_process = match self.post_process.as_mut() {
+            Some(post_process) => post_process,
+            None => return false,
+        };
+
+        let removed = post_process.remove_effect(name);
+        if post_process.effect_names().is_empty() {
+            self.post_process = None;
         }
-        glfw::WindowEvent::Key(Key::N, _, Action::Release, _) => {
-            *z_rotate_ccwise = false;
+
+        removed
+    }
+
+    fn bind_uniform_blocks(&self, model: &Model) -> Result<(), String> {
+        if let Some(program) = model.program.as_ref() {
+            self.camera_buffer.bind_program(program)?;
+            self.lights_buffer.bind_program(program)?;
         }
 
-        glfw::WindowEvent::Key(Key::Num3, _, Action::Press, _) => {
-            *mixvalue_grow = true;
+        Ok(())
+    }
+
+    // Replaces the scene graph, lights, shadow settings, post effects (and text manager) with the ones described
+    // by a scene file, see scene_file.rs for the format
+    pub fn load_scene(&mut self, path: &str) -> Result<(), String> {
+        let scene = scene_file::load_scene(path)?;
+
+        for node in scene.scene.iter() {
+            if let Some(model) = node.model.as_ref() {
+                self.bind_uniform_blocks(model)?;
+            }
         }
-        glfw::WindowEvent::Key(Key::Num3, _, Action::Release, _) => {
-            *mixvalue_grow = false;
+
+        self.scene = scene.scene;
+        self.lights = scene.lights;
+
+        match scene.shadows {
+            Some(settings) => self.enable_shadows(settings)?,
+            None => self.disable_shadows(),
         }
-        glfw::WindowEvent::Key(Key::Num1, _, Action::Press, _) => {
-            *mixvalue_shrink = true;
+
+        self.post_process = None;
+        for (name, effect) in scene.post_effects {
+            self.add_post_effect(&name, effect)?;
         }
-        glfw::WindowEvent::Key(Key::Num1, _, Action::Release, _) => {
-            *mixvalue_shrink = false;
+
+        if let Some(text_manager) = scene.text_manager {
+            self.attach_text_manager(text_manager);
         }
 
-        glfw::WindowEvent::Key(Key::W, _, Action::Press, _) => unsafe {
-            gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
-        },
-        glfw::WindowEvent::Key(Key::F, _, Action::Press, _) => unsafe {
-            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL)
-        },
-        _ => {}
+        Ok(())
     }
 }
@@ -0,0 +1,173 @@
+// Where a packed rectangle ended up in the atlas, in texels
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    // How much of the shelf's width is taken, from the left
+    used: u32,
+}
+
+/*
+ * Packs rectangles into a width x height area in rows (shelves) stacked from
+ * the top, without any GL so it can be checked on its own. A rectangle goes on
+ * the shelf it fits on with the least height to spare, or on a new shelf
+ * below the others when none does. padding texels are kept free left of and
+ * above every rectangle so filtering doesn't bleed neighbours into each other.
+ */
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    padding: u32,
+    shelves: Vec<Shelf>,
+}
+
+impl ShelfPacker {
+    pub fn new(width: u32, height: u32, padding: u32) -> ShelfPacker {
+        ShelfPacker {
+            width: width,
+            height: height,
+            padding: padding,
+            shelves: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // None when the rectangle doesn't fit anymore
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<AtlasRect> {
+        let padded_width = width + self.padding;
+        let padded_height = height + self.padding;
+
+        if padded_width > self.width || padded_height > self.height {
+            return None;
+        }
+
+        let padding = self.padding;
+        let free_width = self.width;
+        let best = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= padded_height && free_width - shelf.used >= padded_width)
+            .min_by_key(|shelf| shelf.height - padded_height);
+
+        let shelf = match best {
+            Some(shelf) => shelf,
+            None => {
+                let top = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+                if top + padded_height > self.height {
+                    return None;
+                }
+
+                self.shelves.push(Shelf {
+                    y: top,
+                    height: padded_height,
+                    used: 0,
+                });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+
+        let rect = AtlasRect {
+            x: shelf.used + padding,
+            y: shelf.y + padding,
+            width: width,
+            height: height,
+        };
+        shelf.used += padded_width;
+
+        Some(rect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // With the padding left of and above it
+    fn padded(rect: &AtlasRect, padding: u32) -> (u32, u32, u32, u32) {
+        (rect.x - padding, rect.y - padding, rect.x + rect.width, rect.y + rect.height)
+    }
+
+    #[test]
+    fn rectangles_keep_apart() {
+        let padding = 2;
+        let mut packer = ShelfPacker::new(128, 128, padding);
+        let mut rects = Vec::new();
+        for i in 0..200u32 {
+            let (width, height) = (3 + i * 7 % 13, 2 + i * 5 % 17);
+            if let Some(rect) = packer.pack(width, height) {
+                assert_eq!((rect.width, rect.height), (width, height));
+                rects.push(rect);
+            }
+        }
+        assert!(rects.len() > 50);
+
+        for (i, a) in rects.iter().enumerate() {
+            let (left, top, right, bottom) = padded(a, padding);
+            assert!(a.x >= padding && a.y >= padding);
+            assert!(right <= 128 && bottom <= 128);
+
+            for b in rects[i + 1..].iter() {
+                let (other_left, other_top, other_right, other_bottom) = padded(b, padding);
+                let apart = right <= other_left || other_right <= left || bottom <= other_top || other_bottom <= top;
+                assert!(apart, "{:?} and {:?} overlap", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn best_fitting_shelf_is_used() {
+        let mut packer = ShelfPacker::new(64, 64, 1);
+        let tall = packer.pack(60, 20).unwrap();
+        // Too wide for what's left of the first shelf
+        let short = packer.pack(60, 10).unwrap();
+        assert_eq!(tall.y, 1);
+        assert_eq!(short.y, 22);
+
+        // Fits on both, the short shelf wastes less
+        let rect = packer.pack(2, 8).unwrap();
+        assert_eq!((rect.x, rect.y), (62, 22));
+        // Only fits on the tall one
+        let rect = packer.pack(2, 15).unwrap();
+        assert_eq!((rect.x, rect.y), (62, 1));
+    }
+
+    #[test]
+    fn new_shelf_when_none_fits() {
+        let mut packer = ShelfPacker::new(64, 64, 0);
+        assert_eq!(packer.pack(10, 10), Some(AtlasRect { x: 0, y: 0, width: 10, height: 10 }));
+        assert_eq!(packer.pack(10, 10), Some(AtlasRect { x: 10, y: 0, width: 10, height: 10 }));
+        // Taller than the shelf
+        assert_eq!(packer.pack(5, 12), Some(AtlasRect { x: 0, y: 10, width: 5, height: 12 }));
+        // Wider than what's left of both
+        assert_eq!(packer.pack(60, 4), Some(AtlasRect { x: 0, y: 22, width: 60, height: 4 }));
+    }
+
+    #[test]
+    fn none_when_it_does_not_fit() {
+        let mut packer = ShelfPacker::new(16, 16, 0);
+        assert_eq!(packer.pack(17, 1), None);
+        assert_eq!(packer.pack(1, 17), None);
+        assert!(packer.pack(16, 16).is_some());
+        assert_eq!(packer.pack(1, 1), None);
+
+        // The padding has to fit too
+        let mut packer = ShelfPacker::new(16, 16, 1);
+        assert_eq!(packer.pack(16, 1), None);
+        assert!(packer.pack(15, 15).is_some());
+        assert_eq!(packer.pack(1, 1), None);
+    }
+}
@@ -0,0 +1,380 @@
+use glam::*;
+
+const DEFAULT_FOV: f32 = 45.0;
+const DEFAULT_TOP_DOWN_EXTENT: f32 = 2.0;
+const MAX_PITCH: f32 = 89.95;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CameraMode {
+    // Walks on the ground, ground_height above y = 0
+    Fps,
+    // Flies wherever it's pointed
+    FreeFly,
+    // Circles target at orbit_distance, yaw and pitch turn it around the target
+    Orbit,
+    // Looks straight down with an orthographic projection, yaw turns the map
+    TopDown,
+}
+
+impl CameraMode {
+    // For cycling through the modes with a key
+    pub fn next(self) -> CameraMode {
+        match self {
+            CameraMode::Fps => CameraMode::FreeFly,
+            CameraMode::FreeFly => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::TopDown,
+            CameraMode::TopDown => CameraMode::Fps,
+        }
+    }
+}
+
+/*
+ * View and projection of the scene, without any GL so it can be driven and
+ * checked on its own. Angles are in degrees, yaw -90 looks down the negative
+ * Z axis. In Orbit mode position follows from target, yaw, pitch and
+ * orbit_distance, change those rather than position.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    mode: CameraMode,
+    pub position: Vec3,
+    yaw: f32,
+    pitch: f32,
+    fov: f32,
+    pub aspect_ratio: f32,
+    pub near: f32,
+    pub far: f32,
+    pub ground_height: f32,
+    pub target: Vec3,
+    orbit_distance: f32,
+    // Half the height of what TopDown shows
+    top_down_extent: f32,
+    pub top_down_height: f32,
+}
+
+impl Camera {
+    pub fn new(position: Vec3, yaw: f32, pitch: f32, aspect_ratio: f32) -> Camera {
+        Camera {
+            mode: CameraMode::Fps,
+            position: position,
+            yaw: yaw,
+            pitch: pitch.clamp(-MAX_PITCH, MAX_PITCH),
+            fov: DEFAULT_FOV,
+            aspect_ratio: aspect_ratio,
+            near: 0.1,
+            far: 100.0,
+            ground_height: position.y,
+            target: Vec3::ZERO,
+            orbit_distance: position.length().max(0.1),
+            top_down_extent: DEFAULT_TOP_DOWN_EXTENT,
+            top_down_height: 10.0,
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    pub fn orbit_distance(&self) -> f32 {
+        self.orbit_distance
+    }
+
+    // Switching keeps the view where it can: Orbit circles whatever is in
+    // front of the camera, Fps puts it back on the ground
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        match mode {
+            CameraMode::Orbit if self.mode != CameraMode::Orbit => {
+                self.target = self.position + self.front() * self.orbit_distance;
+            }
+            CameraMode::Fps => self.position.y = self.ground_height,
+            _ => {}
+        }
+
+        self.mode = mode;
+        self.update_orbit_position();
+    }
+
+    pub fn set_orbit(&mut self, target: Vec3, distance: f32) {
+        self.target = target;
+        self.orbit_distance = distance.max(0.1);
+        self.update_orbit_position();
+    }
+
+    // Where the camera looks, -Y in TopDown
+    pub fn front(&self) -> Vec3 {
+        if self.mode == CameraMode::TopDown {
+            return Vec3::NEG_Y;
+        }
+
+        let mut direction = Vec3::new(0.0,0.0,0.0);
+        direction.x = self.yaw.to_radians().cos() * self.pitch.to_radians().cos();
+        direction.y = self.pitch.to_radians().sin();
+        direction.z = self.yaw.to_radians().sin() * self.pitch.to_radians().cos();
+
+        direction.normalize()
+    }
+
+    // The way yaw points along the ground
+    fn ground_front(&self) -> Vec3 {
+        Vec3::new(self.yaw.to_radians().cos(), 0.0, self.yaw.to_radians().sin())
+    }
+
+    // TopDown can't use Y as up, it looks along it
+    fn world_up(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::TopDown => self.ground_front(),
+            _ => Vec3::Y,
+        }
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.world_up().cross(-self.front()).normalize()
+    }
+
+    // Mouse look, pitch is kept short of straight up or down
+    pub fn rotate(&mut self, yaw_delta: f32, pitch_delta: f32) {
+        self.yaw += yaw_delta;
+        if self.mode != CameraMode::TopDown {
+            self.pitch = (self.pitch + pitch_delta).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        self.update_orbit_position();
+    }
+
+    /*
+     * Moves by the given distances, what they mean depends on the mode:
+     * Fps walks along the ground and ignores up, FreeFly goes along the view,
+     * Orbit moves towards the target with forwards and pans it with right and
+     * up, TopDown scrolls the map and zooms out with up.
+     */
+    pub fn move_by(&mut self, forwards: f32, right: f32, up: f32) {
+        match self.mode {
+            CameraMode::Fps => {
+                self.position += self.front() * forwards + self.right() * right;
+                self.position.y = self.ground_height;
+            }
+            CameraMode::FreeFly => {
+                self.position += self.front() * forwards + self.right() * right + Vec3::Y * up;
+            }
+            CameraMode::Orbit => {
+                self.orbit_distance = (self.orbit_distance - forwards).max(0.1);
+                self.target += self.right() * right + Vec3::Y * up;
+                self.update_orbit_position();
+            }
+            CameraMode::TopDown => {
+                self.position += self.ground_front() * forwards + self.right() * right;
+                self.top_down_extent = (self.top_down_extent + up).max(0.1);
+            }
+        }
+    }
+
+    // Negative zooms in: the field of view in degrees, or the shown
+    // area by the same proportion in TopDown
+    pub fn zoom(&mut self, delta: f32) {
+        match self.mode {
+            CameraMode::TopDown => {
+                self.top_down_extent =
+                    (self.top_down_extent * (1.0 + delta / DEFAULT_FOV)).max(0.1);
+            }
+            _ => self.fov = (self.fov + delta).clamp(1.0, 120.0),
+        }
+    }
+
+    pub fn reset_zoom(&mut self) {
+        self.fov = DEFAULT_FOV;
+        self.top_down_extent = DEFAULT_TOP_DOWN_EXTENT;
+    }
+
+    // alpha of the way from previous to this one, for drawing in between
+    // updates. Only what updates move smoothly is blended, the rest is this one's
+    pub fn interpolate(&self, previous: &Camera, alpha: f32) -> Camera {
+        let blend = |from: f32, to: f32| from + (to - from) * alpha;
+
+        let mut camera = *self;
+        camera.position = previous.position.lerp(self.position, alpha);
+        camera.yaw = blend(previous.yaw, self.yaw);
+        camera.pitch = blend(previous.pitch, self.pitch);
+        camera.fov = blend(previous.fov, self.fov);
+        camera
+    }
+
+    fn update_orbit_position(&mut self) {
+        if self.mode == CameraMode::Orbit {
+            self.position = self.target - self.front() * self.orbit_distance;
+        }
+    }
+
+    // Where the view is from, TopDown hovers top_down_height over position
+    pub fn eye(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::TopDown => Vec3::new(self.position.x, self.top_down_height, self.position.z),
+            _ => self.position,
+        }
+    }
+
+    // Gram-Schmidt process
+    pub fn view_matrix(&self) -> Mat4 {
+        let eye = self.eye();
+
+        let camera_target = eye + self.front();
+        // For the view matrix's coordinate system we want its z-axis
+        // to be positive and because by convention (in OpenLG)
+        // the camera points towards the neg z-axis we want to negate
+        // the direciton vector.
+        // the name "direction vector" is a misnomer, since it is actually
+        // pointing in the reverse direction of what it is targeting
+        let camera_direction = (eye - camera_target).normalize();
+        // To get the right-axis do a cross product between up and target
+        let camera_right = self.world_up().cross(camera_direction).normalize();
+        // get up axis by crossing camera direction with camera right
+        let camera_up = camera_direction.cross(camera_right);
+
+        // From these 3 vectors we can create a LookAt matrix
+        let rotation = Mat4::from_cols(
+            Vec4::from((camera_right, 0.0)),
+            Vec4::from((camera_up, 0.0)),
+            Vec4::from((camera_direction, 0.0)),
+            Vec4::W,
+        )
+        .transpose();
+
+        let translation = Mat4::from_cols(Vec4::X, Vec4::Y, Vec4::Z, Vec4::from((-eye, 1.0)));
+
+        rotation * translation
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        match self.mode {
+            CameraMode::TopDown => {
+                let half_height = self.top_down_extent;
+                let half_width = half_height * self.aspect_ratio;
+                Mat4::orthographic_rh_gl(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near,
+                    self.far,
+                )
+            }
+            _ => Mat4::perspective_rh_gl(self.fov.to_radians(), self.aspect_ratio, self.near, self.far),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} isn't {:?}", a, b);
+    }
+
+    #[test]
+    fn fps_stays_on_the_ground() {
+        let mut camera = Camera::new(Vec3::new(0.0, 1.5, 3.0), -90.0, 0.0, 1.0);
+        camera.rotate(20.0, 40.0);
+        camera.move_by(2.0, 1.0, 5.0);
+
+        assert_eq!(camera.position.y, 1.5);
+        assert_ne!(camera.position.x, 0.0);
+
+        // Back down after flying
+        camera.set_mode(CameraMode::FreeFly);
+        camera.move_by(1.0, 0.0, 3.0);
+        assert!(camera.position.y > 1.5);
+        camera.set_mode(CameraMode::Fps);
+        assert_eq!(camera.position.y, 1.5);
+    }
+
+    #[test]
+    fn pitch_is_clamped() {
+        let mut camera = Camera::new(Vec3::ZERO, -90.0, 120.0, 1.0);
+        assert_eq!(camera.pitch(), MAX_PITCH);
+
+        camera.rotate(0.0, -500.0);
+        assert_eq!(camera.pitch(), -MAX_PITCH);
+        camera.rotate(0.0, 1000.0);
+        assert_eq!(camera.pitch(), MAX_PITCH);
+        assert!(camera.front().y < 1.0);
+    }
+
+    #[test]
+    fn orbit_circles_what_is_in_front() {
+        let mut camera = Camera::new(Vec3::new(1.0, 0.5, 3.0), -90.0, 0.0, 1.0);
+        let in_front = camera.position + camera.front() * camera.orbit_distance();
+
+        camera.set_mode(CameraMode::Orbit);
+        assert_near(camera.target, in_front);
+        assert_near(camera.position, Vec3::new(1.0, 0.5, 3.0));
+
+        camera.rotate(30.0, 20.0);
+        assert_near(camera.target, in_front);
+        assert_near(camera.position, camera.target - camera.front() * camera.orbit_distance());
+        assert!((camera.position.distance(camera.target) - camera.orbit_distance()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn view_matrix_is_look_at() {
+        let mut camera = Camera::new(Vec3::new(1.0, 2.0, 3.0), -60.0, -25.0, 1.5);
+        let expected = Mat4::look_at_rh(camera.eye(), camera.eye() + camera.front(), Vec3::Y);
+        assert!(camera.view_matrix().abs_diff_eq(expected, 1e-5));
+
+        camera.set_mode(CameraMode::Orbit);
+        camera.rotate(45.0, 10.0);
+        let expected = Mat4::look_at_rh(camera.eye(), camera.target, Vec3::Y);
+        assert!(camera.view_matrix().abs_diff_eq(expected, 1e-5));
+    }
+
+    #[test]
+    fn top_down_is_orthographic() {
+        let mut camera = Camera::new(Vec3::new(0.0, 1.0, 0.0), -90.0, 0.0, 2.0);
+        // Perspective divides by -z
+        assert_eq!(camera.projection_matrix().z_axis.w, -1.0);
+
+        camera.set_mode(CameraMode::TopDown);
+        let projection = camera.projection_matrix();
+        assert_eq!(projection.row(3), Vec4::W);
+        assert_eq!(projection.x_axis.x * camera.aspect_ratio, projection.y_axis.y);
+
+        assert_eq!(camera.front(), Vec3::NEG_Y);
+        assert_eq!(camera.eye().y, camera.top_down_height);
+        assert_near(camera.right(), Vec3::X);
+    }
+
+    #[test]
+    fn interpolate_blends_position_and_angles() {
+        let previous = Camera::new(Vec3::new(0.0, 1.0, 2.0), -90.0, 0.0, 1.0);
+        let mut camera = previous;
+        camera.rotate(40.0, 20.0);
+        camera.zoom(-10.0);
+        camera.move_by(1.0, 0.0, 0.0);
+
+        let halfway = camera.interpolate(&previous, 0.5);
+        assert_near(halfway.position, (previous.position + camera.position) * 0.5);
+        assert_eq!(halfway.yaw(), -70.0);
+        assert_eq!(halfway.pitch(), 10.0);
+        assert_eq!(halfway.fov(), 40.0);
+
+        assert_near(camera.interpolate(&previous, 0.0).position, previous.position);
+        assert_eq!(camera.interpolate(&previous, 1.0).yaw(), camera.yaw());
+    }
+
+    #[test]
+    fn right_at_yaw_minus_90() {
+        let camera = Camera::new(Vec3::ZERO, -90.0, 0.0, 1.0);
+        assert_near(camera.front(), Vec3::NEG_Z);
+        assert_near(camera.right(), Vec3::X);
+    }
+}
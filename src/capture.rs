@@ -0,0 +1,184 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+// Writes RGBA8 rows, top row first, as an 8 bit RGBA PNG
+pub fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    if pixels.len() != width as usize * height as usize * 4 {
+        return Err(format!(
+            "{} bytes of pixels don't make a {}x{} RGBA image",
+            pixels.len(),
+            width,
+            height
+        ));
+    }
+
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                return Err(format!("failed to create '{}': {}", parent.display(), e));
+            }
+        }
+    }
+
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("failed to create '{}': {}", path.display(), e)),
+    };
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let result = encoder
+        .write_header()
+        .and_then(|mut writer| {
+            writer.write_image_data(pixels)?;
+            writer.finish()
+        });
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("failed to write '{}': {}", path.display(), e)),
+    }
+}
+
+// Reads any 8 or 16 bit PNG as RGBA8 rows, top row first: (width, height, pixels)
+pub fn read_png(path: &Path) -> Result<(u32, u32, Vec<u8>), String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("failed to open '{}': {}", path.display(), e)),
+    };
+
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let decode_error = |e: png::DecodingError| format!("failed to decode '{}': {}", path.display(), e);
+
+    let mut reader = decoder.read_info().map_err(decode_error)?;
+    let mut buffer: Vec<u8> = vec![0; reader.output_buffer_size().unwrap_or(0)];
+    let info = reader.next_frame(&mut buffer).map_err(decode_error)?;
+    let pixel_count = info.width as usize * info.height as usize;
+
+    // Grayscale and RGB get an opaque alpha
+    let channels = info.color_type.samples();
+    let mut pixels: Vec<u8> = Vec::with_capacity(pixel_count * 4);
+    for row in buffer.chunks_exact(info.line_size).take(info.height as usize) {
+        for pixel in row[..info.width as usize * channels].chunks_exact(channels) {
+            match pixel {
+                [gray] => pixels.extend_from_slice(&[*gray, *gray, *gray, 255]),
+                [gray, alpha] => pixels.extend_from_slice(&[*gray, *gray, *gray, *alpha]),
+                [r, g, b] => pixels.extend_from_slice(&[*r, *g, *b, 255]),
+                [r, g, b, a] => pixels.extend_from_slice(&[*r, *g, *b, *a]),
+                _ => {
+                    return Err(format!(
+                        "'{}' has an unsupported color type {:?}",
+                        path.display(),
+                        info.color_type
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok((info.width, info.height, pixels))
+}
+
+fn timestamp() -> String {
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    format!("{}_{:03}", since_epoch.as_secs(), since_epoch.subsec_millis())
+}
+
+// Names for the capture keys that won't clash with earlier captures
+pub fn screenshot_path() -> PathBuf {
+    PathBuf::from(format!("screenshots/screenshot_{}.png", timestamp()))
+}
+
+pub fn recording_directory() -> PathBuf {
+    PathBuf::from(format!("recordings/recording_{}", timestamp()))
+}
+
+/*
+ * Dumps frames as directory/frame_00000.png, frame_00001.png, ...
+ * While recording the render loop advances the scene by timestep per frame
+ * instead of the time that actually passed, so the same input always gives
+ * the same frames no matter how long writing the PNGs takes.
+ */
+pub struct FrameRecorder {
+    directory: PathBuf,
+    timestep: f32,
+    frame_count: u32,
+}
+
+impl FrameRecorder {
+    pub fn new(directory: &Path, frames_per_second: f32) -> Result<FrameRecorder, String> {
+        if !(frames_per_second > 0.0) {
+            return Err(format!("invalid recording frame rate {}", frames_per_second));
+        }
+
+        if let Err(e) = std::fs::create_dir_all(directory) {
+            return Err(format!("failed to create '{}': {}", directory.display(), e));
+        }
+
+        Ok(FrameRecorder {
+            directory: directory.to_path_buf(),
+            timestep: 1.0 / frames_per_second,
+            frame_count: 0,
+        })
+    }
+
+    // Seconds of scene time between two recorded frames
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    // Path for the next frame, counts it as recorded
+    pub fn next_frame_path(&mut self) -> PathBuf {
+        let path = self
+            .directory
+            .join(format!("frame_{:05}.png", self.frame_count));
+        self.frame_count += 1;
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_round_trip() {
+        let (width, height) = (3, 2);
+        let pixels: Vec<u8> = (0..width * height * 4).map(|i| (i * 10) as u8).collect();
+        let path = std::env::temp_dir().join(format!("capture_round_trip_{}.png", std::process::id()));
+
+        write_png(&path, width, height, &pixels).unwrap();
+        let read = read_png(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(read.unwrap(), (width, height, pixels));
+    }
+
+    #[test]
+    fn wrong_pixel_count_is_an_error() {
+        let path = std::env::temp_dir().join("capture_wrong_size.png");
+        assert!(write_png(&path, 2, 2, &[0; 12]).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        assert!(read_png(Path::new("does/not/exist.png")).is_err());
+    }
+}
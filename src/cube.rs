@@ -7,70 +7,96 @@ pub struct Cube {
 
 impl Cube {
     pub fn new(side_length: f32, center: (f32, f32, f32)) -> Cube {
-        // Because of textures, each vertex needs 3 copies in the current format
-        // so that each face can have a proper texture
-        let mut vertices_cube: Vec<f32> = Vec::with_capacity((3 + 2) * 3 * 6);
+        // Because of textures and normals, each vertex needs 3 copies in the current
+        // format so that each face can have a proper texture and normal.
+        // The first copy of a corner belongs to a Z face, the second to an X face
+        // and the third to a Y face.
+        let mut vertices_cube: Vec<f32> = Vec::with_capacity((3 + 3 + 2) * 3 * 8);
 
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_A));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Back));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopLeft, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_A));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Right));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopRight, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_A));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Top));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopRight, (1.0, 1.0)));
 
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_B));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Back));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopRight, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_B));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Left));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopLeft, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_B));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Top));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopLeft, (1.0, 1.0)));
 
 
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_C));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Back));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomLeft, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_C));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Right));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomRight, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_C));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Bottom));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopLeft, (1.0, 1.0)));
 
 
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_D));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Back));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomRight, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_D));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Left));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomLeft, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_D));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Bottom));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopRight, (1.0, 1.0)));
 
 
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_E));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Front));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopRight, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_E));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Right));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopLeft, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_E));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Top));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomRight, (1.0, 1.0)));
 
 
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_F));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Front));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopLeft, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_F));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Left));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopRight, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_F));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Top));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomLeft, (1.0, 1.0)));
 
 
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_G));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Front));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomRight, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_G));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Right));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomLeft, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_G));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Bottom));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomLeft, (1.0, 1.0)));
 
 
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_H));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Front));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomLeft, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_H));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Left));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomRight, (1.0, 1.0)));
         vertices_cube.extend_from_slice(&Cube::generate_cube_corner_coords(center, side_length, CubeCorner::COORDS_H));
+        vertices_cube.extend_from_slice(&Cube::generate_face_normal(CubeFace::Bottom));
         vertices_cube.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomRight, (1.0, 1.0)));
 
         // This is just hell, gotta find a generic way to produce cubes..
@@ -107,6 +133,17 @@ impl Cube {
         }
     }
 
+    pub fn generate_face_normal(cube_face: CubeFace) -> [f32;3] {
+        match cube_face {
+            CubeFace::Front => [0.0, 0.0, 1.0],
+            CubeFace::Back => [0.0, 0.0, -1.0],
+            CubeFace::Top => [0.0, 1.0, 0.0],
+            CubeFace::Bottom => [0.0, -1.0, 0.0],
+            CubeFace::Right => [1.0, 0.0, 0.0],
+            CubeFace::Left => [-1.0, 0.0, 0.0],
+        }
+    }
+
     pub fn generate_cube_corner_coords(center_point: (f32,f32,f32), side_length: f32, cube_corner: CubeCorner) -> [f32;3] {
         match cube_corner {
             CubeCorner::COORDS_A => {
@@ -150,6 +187,16 @@ pub enum CubeCorner {
     COORDS_H,
 }
 
+// Named as seen from +Z, looking down the negative Z axis
+pub enum CubeFace {
+    Front,
+    Back,
+    Top,
+    Bottom,
+    Right,
+    Left,
+}
+
 pub enum TextureCorner {
     BottomLeft,
     BottomRight,
@@ -0,0 +1,448 @@
+use crate::gl;
+
+// Formats color attachments can have
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorFormat {
+    Rgba8,
+    // Floating point, for HDR rendering
+    Rgba16F,
+}
+
+impl ColorFormat {
+    fn internal_format(&self) -> gl::types::GLenum {
+        match self {
+            ColorFormat::Rgba8 => gl::RGBA8,
+            ColorFormat::Rgba16F => gl::RGBA16F,
+        }
+    }
+
+    fn pixel_type(&self) -> gl::types::GLenum {
+        match self {
+            ColorFormat::Rgba8 => gl::UNSIGNED_BYTE,
+            ColorFormat::Rgba16F => gl::FLOAT,
+        }
+    }
+}
+
+/*
+ * Renderbuffers are cheaper when the depth is only needed for depth testing,
+ * textures can be sampled afterwards (shadow maps). Depth textures read as the
+ * far plane outside of their borders.
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum DepthAttachment {
+    None,
+    DepthRenderbuffer,
+    DepthStencilRenderbuffer,
+    DepthTexture,
+    DepthStencilTexture,
+}
+
+impl DepthAttachment {
+    fn internal_format(&self) -> gl::types::GLenum {
+        match self {
+            DepthAttachment::None => gl::NONE,
+            DepthAttachment::DepthRenderbuffer | DepthAttachment::DepthTexture => {
+                gl::DEPTH_COMPONENT24
+            }
+            DepthAttachment::DepthStencilRenderbuffer | DepthAttachment::DepthStencilTexture => {
+                gl::DEPTH24_STENCIL8
+            }
+        }
+    }
+
+    fn attachment_point(&self) -> gl::types::GLenum {
+        match self {
+            DepthAttachment::DepthStencilRenderbuffer | DepthAttachment::DepthStencilTexture => {
+                gl::DEPTH_STENCIL_ATTACHMENT
+            }
+            _ => gl::DEPTH_ATTACHMENT,
+        }
+    }
+
+    fn is_texture(&self) -> bool {
+        *self == DepthAttachment::DepthTexture || *self == DepthAttachment::DepthStencilTexture
+    }
+}
+
+// Render target other than the window, with any number of color
+// textures and an optional depth (and stencil) attachment
+pub struct FramebufferDescriptor {
+    framebuffer_id: gl::types::GLuint,
+    color_formats: Vec<ColorFormat>,
+    color_texture_ids: Vec<gl::types::GLuint>,
+    depth: DepthAttachment,
+    // Texture or renderbuffer, depending on depth
+    depth_id: gl::types::GLuint,
+    width: gl::types::GLsizei,
+    height: gl::types::GLsizei,
+}
+
+impl FramebufferDescriptor {
+    pub fn new(
+        width: gl::types::GLsizei,
+        height: gl::types::GLsizei,
+        color_formats: &[ColorFormat],
+        depth: DepthAttachment,
+    ) -> Result<FramebufferDescriptor, String> {
+        let mut framebuffer_id = 0;
+
+        unsafe {
+            gl::GenFramebuffers(1, &mut framebuffer_id);
+        }
+
+        let mut framebuffer = FramebufferDescriptor {
+            framebuffer_id: framebuffer_id,
+            color_formats: color_formats.to_vec(),
+            color_texture_ids: Vec::new(),
+            depth: depth,
+            depth_id: 0,
+            width: width,
+            height: height,
+        };
+
+        framebuffer.allocate()?;
+        Ok(framebuffer)
+    }
+
+    // Framebuffer without color buffers, enough for a shadow map
+    pub fn new_depth_only(
+        width: gl::types::GLsizei,
+        height: gl::types::GLsizei,
+    ) -> Result<FramebufferDescriptor, String> {
+        FramebufferDescriptor::new(width, height, &[], DepthAttachment::DepthTexture)
+    }
+
+    // Creates and attaches the textures and renderbuffers for the current size
+    fn allocate(&mut self) -> Result<(), String> {
+        if self.width <= 0 || self.height <= 0 {
+            return Err(format!(
+                "framebuffer {} can't be {}x{}",
+                self.framebuffer_id, self.width, self.height
+            ));
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer_id);
+        }
+
+        for (idx, format) in self.color_formats.iter().enumerate() {
+            let mut texture_id = 0;
+
+            unsafe {
+                gl::GenTextures(1, &mut texture_id);
+                gl::BindTexture(gl::TEXTURE_2D, texture_id);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    format.internal_format() as i32,
+                    self.width,
+                    self.height,
+                    0,
+                    gl::RGBA,
+                    format.pixel_type(),
+                    std::ptr::null(),
+                );
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0 + idx as u32,
+                    gl::TEXTURE_2D,
+                    texture_id,
+                    0,
+                );
+            }
+
+            self.color_texture_ids.push(texture_id);
+        }
+
+        match self.depth {
+            DepthAttachment::None => {}
+            DepthAttachment::DepthRenderbuffer | DepthAttachment::DepthStencilRenderbuffer => unsafe {
+                gl::GenRenderbuffers(1, &mut self.depth_id);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth_id);
+                gl::RenderbufferStorage(
+                    gl::RENDERBUFFER,
+                    self.depth.internal_format(),
+                    self.width,
+                    self.height,
+                );
+                gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+                gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER,
+                    self.depth.attachment_point(),
+                    gl::RENDERBUFFER,
+                    self.depth_id,
+                );
+            },
+            DepthAttachment::DepthTexture | DepthAttachment::DepthStencilTexture => unsafe {
+                let (format, pixel_type) = if self.depth == DepthAttachment::DepthTexture {
+                    (gl::DEPTH_COMPONENT, gl::FLOAT)
+                } else {
+                    (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8)
+                };
+
+                gl::GenTextures(1, &mut self.depth_id);
+                gl::BindTexture(gl::TEXTURE_2D, self.depth_id);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    self.depth.internal_format() as i32,
+                    self.width,
+                    self.height,
+                    0,
+                    format,
+                    pixel_type,
+                    std::ptr::null(),
+                );
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as i32);
+                let border_color: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+                gl::TexParameterfv(gl::TEXTURE_2D, gl::TEXTURE_BORDER_COLOR, border_color.as_ptr());
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    self.depth.attachment_point(),
+                    gl::TEXTURE_2D,
+                    self.depth_id,
+                    0,
+                );
+            },
+        }
+
+        // Without color attachments nothing is drawn or read from color buffers
+        let draw_buffers: Vec<gl::types::GLenum> = (0..self.color_formats.len())
+            .map(|idx| gl::COLOR_ATTACHMENT0 + idx as u32)
+            .collect();
+        unsafe {
+            if draw_buffers.is_empty() {
+                gl::DrawBuffer(gl::NONE);
+                gl::ReadBuffer(gl::NONE);
+            } else {
+                gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+                gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            }
+        }
+
+        let status = self.check_complete();
+        FramebufferDescriptor::bind_default();
+        status
+    }
+
+    // Deletes the attachments but keeps the framebuffer object
+    fn release(&mut self) {
+        unsafe {
+            gl::DeleteTextures(
+                self.color_texture_ids.len() as i32,
+                self.color_texture_ids.as_ptr(),
+            );
+
+            if self.depth.is_texture() {
+                gl::DeleteTextures(1, &self.depth_id);
+            } else if self.depth != DepthAttachment::None {
+                gl::DeleteRenderbuffers(1, &self.depth_id);
+            }
+        }
+
+        self.color_texture_ids.clear();
+        self.depth_id = 0;
+    }
+
+    // Has to be bound
+    fn check_complete(&self) -> Result<(), String> {
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+
+        let reason = match status {
+            gl::FRAMEBUFFER_COMPLETE => return Ok(()),
+            gl::FRAMEBUFFER_UNDEFINED => "undefined",
+            gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "incomplete attachment",
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "missing attachment",
+            gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "incomplete draw buffer",
+            gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "incomplete read buffer",
+            gl::FRAMEBUFFER_UNSUPPORTED => "unsupported attachment formats",
+            gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "mismatched multisampling",
+            _ => "unknown status",
+        };
+
+        Err(format!(
+            "framebuffer {} ({}x{}) is not complete: {} (0x{:x})",
+            self.framebuffer_id, self.width, self.height, reason, status
+        ))
+    }
+
+    // Reallocates every attachment at the new size, their previous
+    // contents are lost. Texture ids change too.
+    pub fn resize(
+        &mut self,
+        width: gl::types::GLsizei,
+        height: gl::types::GLsizei,
+    ) -> Result<(), String> {
+        if width == self.width && height == self.height {
+            return Ok(());
+        }
+
+        self.release();
+        self.width = width;
+        self.height = height;
+        self.allocate()
+    }
+
+    // Draws go to this framebuffer, the viewport is set to cover all of it
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer_id);
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    // Back to the window, the caller restores the viewport
+    pub fn bind_default() {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    pub fn bind_color_texture(&self, attachment_idx: usize, texture_unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.color_texture_ids[attachment_idx]);
+        }
+    }
+
+    // Only framebuffers with a depth texture have one to bind
+    pub fn bind_depth_texture(&self, texture_unit: u32) {
+        if !self.depth.is_texture() {
+            eprintln!(
+                "WARNING: framebuffer {} has no depth texture to bind",
+                self.framebuffer_id
+            );
+            return;
+        }
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.depth_id);
+        }
+    }
+
+    pub fn color_texture_id(&self, attachment_idx: usize) -> gl::types::GLuint {
+        self.color_texture_ids[attachment_idx]
+    }
+
+    /*
+     * Copies the first color attachment to the window's framebuffer, scaled
+     * to screen_width x screen_height. filter is gl::NEAREST or gl::LINEAR.
+     * Leaves the window's framebuffer bound.
+     */
+    pub fn blit_to_screen(
+        &self,
+        screen_width: gl::types::GLsizei,
+        screen_height: gl::types::GLsizei,
+        filter: gl::types::GLenum,
+    ) {
+        self.blit(0, screen_width, screen_height, filter);
+        FramebufferDescriptor::bind_default();
+    }
+
+    // Same as blit_to_screen() but into the first color attachment of
+    // another framebuffer, which is left bound
+    pub fn blit_to_framebuffer(&self, target: &FramebufferDescriptor, filter: gl::types::GLenum) {
+        self.blit(target.framebuffer_id, target.width, target.height, filter);
+        target.bind();
+    }
+
+    fn blit(
+        &self,
+        draw_framebuffer_id: gl::types::GLuint,
+        width: gl::types::GLsizei,
+        height: gl::types::GLsizei,
+        filter: gl::types::GLenum,
+    ) {
+        if self.color_texture_ids.is_empty() {
+            eprintln!(
+                "WARNING: framebuffer {} has no color attachment to blit",
+                self.framebuffer_id
+            );
+            return;
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer_id);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, draw_framebuffer_id);
+            gl::BlitFramebuffer(
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                width,
+                height,
+                gl::COLOR_BUFFER_BIT,
+                filter,
+            );
+        }
+    }
+
+    // First color attachment as RGBA8, see read_rgba_pixels()
+    pub fn read_pixels(&self) -> Vec<u8> {
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer_id);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+        }
+
+        let pixels = read_rgba_pixels(self.width, self.height);
+        FramebufferDescriptor::bind_default();
+        pixels
+    }
+
+    pub fn width(&self) -> gl::types::GLsizei {
+        self.width
+    }
+
+    pub fn height(&self) -> gl::types::GLsizei {
+        self.height
+    }
+}
+
+// Reads the bound read framebuffer into RGBA8 rows, top row first
+// (OpenGL hands them out bottom row first)
+pub fn read_rgba_pixels(width: gl::types::GLsizei, height: gl::types::GLsizei) -> Vec<u8> {
+    let row_size = width as usize * 4;
+    let mut pixels: Vec<u8> = vec![0; row_size * height as usize];
+
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut gl::types::GLvoid,
+        );
+    }
+
+    let mut flipped: Vec<u8> = Vec::with_capacity(pixels.len());
+    for row in pixels.chunks_exact(row_size).rev() {
+        flipped.extend_from_slice(row);
+    }
+
+    flipped
+}
+
+impl Drop for FramebufferDescriptor {
+    fn drop(&mut self) {
+        self.release();
+
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer_id);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use glam::*;
+use glfw::{Action, GamepadAxis, GamepadButton, Glfw, JoystickId};
+
+use crate::input::{AxisDirection, Binding, InputMap};
+
+// Extra SDL style mappings (gamecontrollerdb.txt) for pads glfw doesn't know
+pub const GAMEPAD_MAPPINGS: &str = "res/gamecontrollerdb.txt";
+
+#[derive(Clone, Copy)]
+pub struct GamepadSettings {
+    // How far (0 to 1) a stick can be off center before it counts
+    pub deadzone: f32,
+    // What's past the deadzone gets raised to this power,
+    // above 1 gives finer control close to the center
+    pub curve: f32,
+    // Same as deadzone for the triggers
+    pub trigger_deadzone: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> GamepadSettings {
+        GamepadSettings {
+            deadzone: 0.15,
+            curve: 2.0,
+            trigger_deadzone: 0.05,
+        }
+    }
+}
+
+// Deadzone and curve applied to how far the stick is pushed, not to
+// each axis on its own, so diagonals don't snap to the axes
+pub fn shape_stick(stick: Vec2, settings: &GamepadSettings) -> Vec2 {
+    let length = stick.length();
+    if length <= settings.deadzone || length == 0.0 {
+        return Vec2::ZERO;
+    }
+
+    let past_deadzone = ((length - settings.deadzone) / (1.0 - settings.deadzone)).min(1.0);
+    stick / length * past_deadzone.powf(settings.curve)
+}
+
+// glfw reports triggers from -1 (released) to 1, this gives 0 to 1
+pub fn shape_trigger(value: f32, settings: &GamepadSettings) -> f32 {
+    let pressed = ((value + 1.0) * 0.5).clamp(0.0, 1.0);
+    if pressed <= settings.trigger_deadzone {
+        return 0.0;
+    }
+
+    (pressed - settings.trigger_deadzone) / (1.0 - settings.trigger_deadzone)
+}
+
+const STICKS: [(GamepadAxis, GamepadAxis); 2] = [
+    (GamepadAxis::AxisLeftX, GamepadAxis::AxisLeftY),
+    (GamepadAxis::AxisRightX, GamepadAxis::AxisRightY),
+];
+
+const TRIGGERS: [GamepadAxis; 2] = [GamepadAxis::AxisLeftTrigger, GamepadAxis::AxisRightTrigger];
+
+/*
+ * Follows the first joystick glfw has a gamepad mapping for, checking every
+ * frame so pads can be plugged in and out while running. Its buttons and
+ * sticks go into the InputMap like keys do, sticks as one binding per
+ * direction ("GamepadLeftY-" is the left stick pushed up).
+ */
+pub struct Gamepads {
+    active: Option<JoystickId>,
+}
+
+impl Gamepads {
+    pub fn new(glfw: &Glfw) -> Gamepads {
+        if let Ok(mappings) = std::fs::read_to_string(GAMEPAD_MAPPINGS) {
+            if !glfw.update_gamepad_mappings(&mappings) {
+                eprintln!("WARNING: failed to load gamepad mappings from {}", GAMEPAD_MAPPINGS);
+            }
+        }
+
+        Gamepads { active: None }
+    }
+
+    pub fn poll(&mut self, glfw: &Glfw, input: &mut InputMap) {
+        let gamepad = (0..=glfw::ffi::JOYSTICK_LAST)
+            .filter_map(JoystickId::from_i32)
+            .map(|id| glfw.get_joystick(id))
+            .find(|joystick| joystick.is_present() && joystick.is_gamepad());
+
+        let id = gamepad.as_ref().map(|joystick| joystick.id);
+        if id != self.active {
+            match gamepad.as_ref() {
+                Some(joystick) => println!(
+                    "Gamepad connected: {}",
+                    joystick.get_gamepad_name().unwrap_or("unknown".to_string())
+                ),
+                None => println!("Gamepad disconnected"),
+            }
+            self.active = id;
+        }
+
+        let state = match gamepad.and_then(|joystick| joystick.get_gamepad_state()) {
+            Some(state) => state,
+            None => {
+                release_all(input);
+                return;
+            }
+        };
+
+        let settings = *input.gamepad_settings();
+
+        for button in (0..=glfw::ffi::GAMEPAD_BUTTON_LAST).filter_map(GamepadButton::from_i32) {
+            let down = state.get_button_state(button) != Action::Release;
+            input.set_down(Binding::GamepadButton(button), down);
+        }
+
+        for (x_axis, y_axis) in STICKS.iter() {
+            let stick = shape_stick(
+                Vec2::new(state.get_axis(*x_axis), state.get_axis(*y_axis)),
+                &settings,
+            );
+            set_axis(input, *x_axis, stick.x);
+            set_axis(input, *y_axis, stick.y);
+        }
+
+        for trigger in TRIGGERS.iter() {
+            let value = shape_trigger(state.get_axis(*trigger), &settings);
+            input.set_analog(Binding::GamepadAxis(*trigger, AxisDirection::Positive), value);
+        }
+    }
+}
+
+fn set_axis(input: &mut InputMap, axis: GamepadAxis, value: f32) {
+    input.set_analog(Binding::GamepadAxis(axis, AxisDirection::Positive), value.max(0.0));
+    input.set_analog(Binding::GamepadAxis(axis, AxisDirection::Negative), (-value).max(0.0));
+}
+
+// So nothing stays held when the pad goes away
+fn release_all(input: &mut InputMap) {
+    for button in (0..=glfw::ffi::GAMEPAD_BUTTON_LAST).filter_map(GamepadButton::from_i32) {
+        input.set_down(Binding::GamepadButton(button), false);
+    }
+
+    for (x_axis, y_axis) in STICKS.iter() {
+        set_axis(input, *x_axis, 0.0);
+        set_axis(input, *y_axis, 0.0);
+    }
+
+    for trigger in TRIGGERS.iter() {
+        input.set_analog(Binding::GamepadAxis(*trigger, AxisDirection::Positive), 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear() -> GamepadSettings {
+        GamepadSettings {
+            curve: 1.0,
+            ..GamepadSettings::default()
+        }
+    }
+
+    #[test]
+    fn stick_deadzone_and_curve() {
+        let settings = GamepadSettings::default();
+
+        assert_eq!(shape_stick(Vec2::ZERO, &settings), Vec2::ZERO);
+        assert_eq!(shape_stick(Vec2::new(0.1, -0.1), &settings), Vec2::ZERO);
+        assert_eq!(shape_stick(Vec2::new(0.0, -1.0), &settings), Vec2::new(0.0, -1.0));
+
+        // Halfway past the deadzone, squared by the curve
+        let halfway = (1.0 + settings.deadzone) * 0.5;
+        let shaped = shape_stick(Vec2::new(halfway, 0.0), &settings);
+        assert!((shaped.x - 0.25).abs() < 1e-5);
+        assert_eq!(shaped.y, 0.0);
+
+        let shaped = shape_stick(Vec2::new(halfway, 0.0), &linear());
+        assert!((shaped.x - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stick_keeps_its_direction() {
+        let stick = Vec2::new(0.3, 0.4);
+        let shaped = shape_stick(stick, &linear());
+        assert!((shaped.normalize() - stick.normalize()).length() < 1e-5);
+
+        // Corners report more than 1 on some pads
+        let shaped = shape_stick(Vec2::new(1.0, 1.0), &GamepadSettings::default());
+        assert!((shaped.length() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn trigger_range_and_deadzone() {
+        let settings = GamepadSettings::default();
+
+        assert_eq!(shape_trigger(-1.0, &settings), 0.0);
+        assert_eq!(shape_trigger(-0.95, &settings), 0.0);
+        assert_eq!(shape_trigger(1.0, &settings), 1.0);
+        assert!((shape_trigger(0.05, &settings) - 0.5).abs() < 1e-5);
+        // Out of range readings are clamped
+        assert_eq!(shape_trigger(-1.5, &settings), 0.0);
+        assert_eq!(shape_trigger(1.5, &settings), 1.0);
+    }
+}
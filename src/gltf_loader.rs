@@ -0,0 +1,269 @@
+use glam::*;
+
+use crate::gl;
+use crate::texture::TextureDescriptor;
+use crate::vertex::{AttributesDescriptor, Mesh, Model, ModelNode};
+
+// CPU side copy of a glTF primitive, vertices are interleaved as
+// 3 position, 3 normal and 2 texture components like the assimp loader does
+pub struct GltfMeshData {
+    pub vertices: Vec<f32>,
+    pub face_indices: Vec<u32>,
+    pub base_color_image: Option<usize>,
+}
+
+// Decoded PNG/JPEG image, either embedded in the file or referenced by it
+pub struct GltfImageData {
+    pub width: u32,
+    pub height: u32,
+    pub format: gl::types::GLenum,
+    pub pixels: Vec<u8>,
+}
+
+// Everything parse_gltf() pulls out of a file, mesh indices of the nodes
+// point into meshes
+pub struct GltfSceneData {
+    pub meshes: Vec<GltfMeshData>,
+    pub images: Vec<GltfImageData>,
+    pub nodes: Vec<ModelNode>,
+}
+
+// Reads a .gltf file (external or data URI buffers) or a binary .glb file.
+// Nothing here touches OpenGL, so it can run without a context.
+pub fn parse_gltf(path: &str) -> Result<GltfSceneData, String> {
+    let (document, buffers, images) = match gltf::import(path) {
+        Ok(imported) => imported,
+        Err(e) => {
+            return Err(format!("Failed to import {}: {}", path, e));
+        }
+    };
+
+    let mut meshes: Vec<GltfMeshData> = Vec::new();
+    // A glTF mesh is made of several primitives, each one becomes one of our
+    // meshes, so keep track of where every glTF mesh ended up
+    let mut mesh_primitives: Vec<Vec<usize>> = Vec::new();
+
+    for gltf_mesh in document.meshes() {
+        let mut primitives = Vec::new();
+
+        for primitive in gltf_mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                eprintln!(
+                    "Skipping primitive {} of mesh {} in {}: only triangles are supported",
+                    primitive.index(),
+                    gltf_mesh.index(),
+                    path
+                );
+                continue;
+            }
+
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()].0[..]));
+
+            let positions: Vec<[f32; 3]> = match reader.read_positions() {
+                Some(positions) => positions.collect(),
+                None => {
+                    return Err(format!(
+                        "Primitive {} of mesh {} in {} has no positions",
+                        primitive.index(),
+                        gltf_mesh.index(),
+                        path
+                    ));
+                }
+            };
+            let normals: Vec<[f32; 3]> = reader
+                .read_normals()
+                .map(|normals| normals.collect())
+                .unwrap_or_default();
+            let tex_coords: Vec<[f32; 2]> = reader
+                .read_tex_coords(0)
+                .map(|tex_coords| tex_coords.into_f32().collect())
+                .unwrap_or_default();
+
+            let mut vertices: Vec<f32> = Vec::with_capacity(positions.len() * (3 + 3 + 2));
+            for (idx, position) in positions.iter().enumerate() {
+                vertices.extend_from_slice(position);
+                vertices.extend_from_slice(normals.get(idx).unwrap_or(&[0.0, 0.0, 0.0]));
+                vertices.extend_from_slice(tex_coords.get(idx).unwrap_or(&[0.0, 0.0]));
+            }
+
+            // Non-indexed primitives draw their vertices in order
+            let face_indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            let base_color_image = primitive
+                .material()
+                .pbr_metallic_roughness()
+                .base_color_texture()
+                .map(|info| info.texture().source().index());
+
+            primitives.push(meshes.len());
+            meshes.push(GltfMeshData {
+                vertices: vertices,
+                face_indices: face_indices,
+                base_color_image: base_color_image,
+            });
+        }
+
+        mesh_primitives.push(primitives);
+    }
+
+    let mut image_data: Vec<GltfImageData> = Vec::with_capacity(images.len());
+    for image in images {
+        let format = match image.format {
+            gltf::image::Format::R8 => gl::RED,
+            gltf::image::Format::R8G8 => gl::RG,
+            gltf::image::Format::R8G8B8 => gl::RGB,
+            gltf::image::Format::R8G8B8A8 => gl::RGBA,
+            other => {
+                return Err(format!("Unsupported image format {:?} in {}", other, path));
+            }
+        };
+
+        image_data.push(GltfImageData {
+            width: image.width,
+            height: image.height,
+            format: format,
+            pixels: image.pixels,
+        });
+    }
+
+    let nodes = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => scene
+            .nodes()
+            .map(|node| parse_node(&node, &mesh_primitives))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(GltfSceneData {
+        meshes: meshes,
+        images: image_data,
+        nodes: nodes,
+    })
+}
+
+fn parse_node(node: &gltf::Node, mesh_primitives: &Vec<Vec<usize>>) -> ModelNode {
+    ModelNode {
+        name: node.name().unwrap_or("").to_string(),
+        transform: Mat4::from_cols_array_2d(&node.transform().matrix()),
+        meshes: node
+            .mesh()
+            .map(|gltf_mesh| mesh_primitives[gltf_mesh.index()].clone())
+            .unwrap_or_default(),
+        children: node
+            .children()
+            .map(|child| parse_node(&child, mesh_primitives))
+            .collect(),
+    }
+}
+
+// Parses a glTF file and uploads it, base color textures are bound
+// to the texture_diffuse1 sampler of the given program
+pub fn load_gltf(path: &str, program_id: gl::types::GLuint) -> Result<Model, String> {
+    let scene = parse_gltf(path)?;
+
+    // Images are shared between materials, upload each of them only once
+    let mut textures: Vec<Option<TextureDescriptor>> = Vec::new();
+    textures.resize_with(scene.images.len(), || None);
+
+    let mut model = Model::new();
+
+    for mesh_data in scene.meshes {
+        let attributes = AttributesDescriptor {
+            component_groups: 3,
+            component_nums: vec![3, 3, 2],
+            component_types: vec![gl::FLOAT, gl::FLOAT, gl::FLOAT],
+            component_offsets: vec![0, 3, 6],
+            component_strides: vec![8, 8, 8],
+        };
+
+        let mut mesh = Mesh::new(mesh_data.vertices, mesh_data.face_indices, attributes);
+
+        if let Some(image_idx) = mesh_data.base_color_image {
+            let image = &scene.images[image_idx];
+            let texture = textures[image_idx].get_or_insert_with(|| {
+                TextureDescriptor::from_pixels(
+                    program_id,
+                    "texture_diffuse1",
+                    image.width as i32,
+                    image.height as i32,
+                    image.format,
+                    &image.pixels,
+                )
+            });
+            mesh.add_texture(texture.clone());
+        }
+
+        model.add_mesh(mesh);
+    }
+
+    model.nodes = scene.nodes;
+
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An indexed quad and a triangle without indices, in one mesh
+    // under a rotated and scaled node, itself under a translated one
+    const SHAPES: &str = "res/tests/shapes.gltf";
+
+    #[test]
+    fn vertices_and_indices() {
+        let scene = parse_gltf(SHAPES).unwrap();
+        assert_eq!(scene.meshes.len(), 2);
+        assert!(scene.images.is_empty());
+
+        let quad = &scene.meshes[0];
+        assert_eq!(quad.vertices.len(), 4 * 8);
+        assert_eq!(quad.face_indices, vec![0, 1, 2, 0, 2, 3]);
+        // Position, normal and texture coordinates of the second vertex
+        assert_eq!(&quad.vertices[8..16], &[0.5, -0.5, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        assert_eq!(quad.base_color_image, None);
+
+        // Missing normals and texture coordinates are zeroed
+        let triangle = &scene.meshes[1];
+        assert_eq!(triangle.vertices.len(), 3 * 8);
+        assert_eq!(triangle.face_indices, vec![0, 1, 2]);
+        assert_eq!(&triangle.vertices[8..16], &[1.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn node_hierarchy() {
+        let scene = parse_gltf(SHAPES).unwrap();
+        assert_eq!(scene.nodes.len(), 1);
+
+        let root = &scene.nodes[0];
+        assert_eq!(root.name, "root");
+        assert!(root.meshes.is_empty());
+        assert!(root.transform.abs_diff_eq(Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)), 1e-6));
+        assert_eq!(root.children.len(), 1);
+
+        let shapes = &root.children[0];
+        assert_eq!(shapes.name, "shapes");
+        assert_eq!(shapes.meshes, vec![0, 1]);
+        assert!(shapes.children.is_empty());
+        let expected = Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0),
+            Quat::from_rotation_y(90.0_f32.to_radians()),
+            Vec3::ZERO,
+        );
+        assert!(shapes.transform.abs_diff_eq(expected, 1e-5));
+
+        // +X of the quad ends up along -Z, twice as long and moved by the root
+        let world = root.transform * shapes.transform;
+        let corner = world.transform_point3(Vec3::new(0.5, 0.0, 0.0));
+        assert!(corner.abs_diff_eq(Vec3::new(1.0, 2.0, 2.0), 1e-5));
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let result = parse_gltf("res/tests/missing.gltf");
+        assert!(result.is_err());
+        assert!(result.err().unwrap().contains("missing.gltf"));
+    }
+}
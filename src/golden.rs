@@ -0,0 +1,457 @@
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use glam::*;
+use serde::Deserialize;
+
+use crate::application::{Application, FrameView};
+use crate::capture;
+
+pub const GOLDEN_MANIFEST: &str = "res/golden/golden.toml";
+// References live next to the manifest as <name>.png
+pub const GOLDEN_REFERENCE_DIRECTORY: &str = "res/golden";
+// What failing cases rendered and where they differ, <name>_actual.png and <name>_diff.png
+pub const GOLDEN_OUTPUT_DIRECTORY: &str = "target/golden";
+
+/*
+ * A pixel only fails when it's off by more than channel in some channel and
+ * by more than perceptual in YIQ color space, the image fails when more than
+ * max_failing_fraction of its pixels do. The channel limit absorbs rounding
+ * between drivers, the perceptual one differences the eye can't tell apart.
+ */
+#[derive(Clone, Copy)]
+pub struct Tolerance {
+    pub channel: u8,
+    // 0 is identical, 1 the largest difference two colors can have
+    pub perceptual: f32,
+    pub max_failing_fraction: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Tolerance {
+        Tolerance {
+            channel: 8,
+            perceptual: 0.02,
+            max_failing_fraction: 0.001,
+        }
+    }
+}
+
+pub struct ImageDiff {
+    pub total_pixels: usize,
+    // Differ, but within the tolerance
+    pub tolerated_pixels: usize,
+    pub failing_pixels: usize,
+    pub max_channel_difference: u8,
+    pub max_perceptual_difference: f32,
+    // RGBA8 rows, top row first: the reference faded out, tolerated
+    // pixels in yellow and failing ones in red
+    pub diff_image: Vec<u8>,
+}
+
+impl ImageDiff {
+    pub fn failing_fraction(&self) -> f32 {
+        if self.total_pixels == 0 {
+            return 0.0;
+        }
+        self.failing_pixels as f32 / self.total_pixels as f32
+    }
+
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        self.failing_fraction() <= tolerance.max_failing_fraction
+    }
+}
+
+// Both images as RGBA8 rows of width x height pixels
+pub fn compare_images(
+    reference: &[u8],
+    actual: &[u8],
+    width: u32,
+    height: u32,
+    tolerance: &Tolerance,
+) -> Result<ImageDiff, String> {
+    let total_pixels = width as usize * height as usize;
+    if reference.len() != total_pixels * 4 || actual.len() != total_pixels * 4 {
+        return Err(format!(
+            "images of {} and {} bytes can't both be {}x{} RGBA",
+            reference.len(),
+            actual.len(),
+            width,
+            height
+        ));
+    }
+
+    let mut diff = ImageDiff {
+        total_pixels: total_pixels,
+        tolerated_pixels: 0,
+        failing_pixels: 0,
+        max_channel_difference: 0,
+        max_perceptual_difference: 0.0,
+        diff_image: Vec::with_capacity(total_pixels * 4),
+    };
+
+    for (expected, found) in reference.chunks_exact(4).zip(actual.chunks_exact(4)) {
+        let channel_difference = expected
+            .iter()
+            .zip(found.iter())
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+
+        if channel_difference == 0 {
+            let faded = 255 - (255 - luminance(expected)) / 10;
+            diff.diff_image.extend_from_slice(&[faded, faded, faded, 255]);
+            continue;
+        }
+
+        let perceptual_difference = perceptual_difference(expected, found);
+        diff.max_channel_difference = diff.max_channel_difference.max(channel_difference);
+        diff.max_perceptual_difference = diff.max_perceptual_difference.max(perceptual_difference);
+
+        if channel_difference > tolerance.channel && perceptual_difference > tolerance.perceptual {
+            diff.failing_pixels += 1;
+            diff.diff_image.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            diff.tolerated_pixels += 1;
+            diff.diff_image.extend_from_slice(&[255, 210, 0, 255]);
+        }
+    }
+
+    Ok(diff)
+}
+
+// Pixels blended over white, like they'd look in an image viewer
+fn blend_over_white(pixel: &[u8]) -> Vec3 {
+    let alpha = pixel[3] as f32 / 255.0;
+    let color = Vec3::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32);
+    Vec3::splat(255.0) + (color - Vec3::splat(255.0)) * alpha
+}
+
+fn luminance(pixel: &[u8]) -> u8 {
+    let color = blend_over_white(pixel);
+    (color.x * 0.299 + color.y * 0.587 + color.z * 0.114).round() as u8
+}
+
+/*
+ * Weighted distance in YIQ space, from "Measuring perceived color difference
+ * using YIQ NTSC transmission color space in mobile applications"
+ * (Kotsarenko and Ramos), scaled so the largest difference between any two
+ * colors is 1 (black against white is 0.93).
+ */
+fn perceptual_difference(a: &[u8], b: &[u8]) -> f32 {
+    const MAX_DELTA: f32 = 35215.0;
+
+    let yiq = |color: Vec3| {
+        Vec3::new(
+            color.dot(Vec3::new(0.29889531, 0.58662247, 0.11448223)),
+            color.dot(Vec3::new(0.59597799, -0.27417610, -0.32180189)),
+            color.dot(Vec3::new(0.21147017, -0.52261711, 0.31114694)),
+        )
+    };
+
+    let delta = yiq(blend_over_white(a)) - yiq(blend_over_white(b));
+    let weighted = 0.5053 * delta.x * delta.x + 0.299 * delta.y * delta.y + 0.1957 * delta.z * delta.z;
+
+    (weighted / MAX_DELTA).min(1.0)
+}
+
+/*
+ * One rendered view of a scene file in res/golden/golden.toml:
+ *
+ * [[case]]
+ * name = "room"                    reference is res/golden/room.png
+ * scene = "res/scene.toml"
+ * width = 320                      optional, 320 x 240 by default
+ * height = 240
+ * camera_position = [0.0, 0.2, 1.0]
+ * camera_target = [0.0, 0.0, -0.4]
+ * fov = 45.0                       optional, degrees
+ * frames = 1                       optional, rendered before the one compared
+ * channel_tolerance = 8            optional, see Tolerance
+ * perceptual_tolerance = 0.02
+ * max_failing_fraction = 0.001
+ */
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GoldenFile {
+    #[serde(default, rename = "case")]
+    cases: Vec<GoldenCase>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GoldenCase {
+    name: String,
+    scene: String,
+    #[serde(default = "default_width")]
+    width: i32,
+    #[serde(default = "default_height")]
+    height: i32,
+    camera_position: [f32; 3],
+    camera_target: [f32; 3],
+    #[serde(default = "default_fov")]
+    fov: f32,
+    #[serde(default = "default_frames")]
+    frames: u32,
+    channel_tolerance: Option<u8>,
+    perceptual_tolerance: Option<f32>,
+    max_failing_fraction: Option<f32>,
+}
+
+fn default_width() -> i32 {
+    320
+}
+
+fn default_height() -> i32 {
+    240
+}
+
+fn default_fov() -> f32 {
+    45.0
+}
+
+fn default_frames() -> u32 {
+    1
+}
+
+impl GoldenCase {
+    fn tolerance(&self) -> Tolerance {
+        let default = Tolerance::default();
+        Tolerance {
+            channel: self.channel_tolerance.unwrap_or(default.channel),
+            perceptual: self.perceptual_tolerance.unwrap_or(default.perceptual),
+            max_failing_fraction: self.max_failing_fraction.unwrap_or(default.max_failing_fraction),
+        }
+    }
+
+    fn reference_path(&self) -> PathBuf {
+        Path::new(GOLDEN_REFERENCE_DIRECTORY).join(format!("{}.png", self.name))
+    }
+
+    fn output_path(&self, suffix: &str) -> PathBuf {
+        Path::new(GOLDEN_OUTPUT_DIRECTORY).join(format!("{}_{}.png", self.name, suffix))
+    }
+
+    fn render(&self) -> Result<Vec<u8>, String> {
+        if self.width <= 0 || self.height <= 0 {
+            return Err(format!("invalid size {}x{}", self.width, self.height));
+        }
+
+        let mut app = Application::new_headless(self.width, self.height)?;
+        app.load_scene(&self.scene)?;
+
+        let view = FrameView::looking_at(
+            Vec3::from(self.camera_position),
+            Vec3::from(self.camera_target),
+            self.fov,
+            self.width as f32 / self.height as f32,
+        );
+
+        app.render_offscreen(&view, self.frames + 1, self.width, self.height)
+    }
+
+    // Ok(true) when the case passed or its reference was written
+    fn run(&self, update: bool) -> Result<bool, String> {
+        let reference_path = self.reference_path();
+
+        // Checked before rendering, a run that compares nothing mustn't pass
+        if !update && !reference_path.is_file() {
+            return Err(format!(
+                "no reference image '{}', create it with --golden --update {}",
+                reference_path.display(),
+                self.name
+            ));
+        }
+
+        let actual = self.render()?;
+        let (width, height) = (self.width as u32, self.height as u32);
+
+        if update {
+            capture::write_png(&reference_path, width, height, &actual)?;
+            println!("UPDATED {} ({})", self.name, reference_path.display());
+            return Ok(true);
+        }
+
+        let (reference_width, reference_height, reference) = capture::read_png(&reference_path)?;
+        if reference_width != width || reference_height != height {
+            return Err(format!(
+                "reference is {}x{} but the case renders {}x{}",
+                reference_width, reference_height, width, height
+            ));
+        }
+
+        let tolerance = self.tolerance();
+        let diff = compare_images(&reference, &actual, width, height, &tolerance)?;
+
+        if diff.passes(&tolerance) {
+            println!(
+                "PASS {} ({} pixels differ within the tolerance)",
+                self.name, diff.tolerated_pixels
+            );
+            return Ok(true);
+        }
+
+        let actual_path = self.output_path("actual");
+        let diff_path = self.output_path("diff");
+        capture::write_png(&actual_path, width, height, &actual)?;
+        capture::write_png(&diff_path, width, height, &diff.diff_image)?;
+
+        println!(
+            "FAIL {}: {} of {} pixels differ ({:.3}%, {:.3}% allowed), largest channel difference {}, largest perceptual difference {:.4}",
+            self.name,
+            diff.failing_pixels,
+            diff.total_pixels,
+            diff.failing_fraction() * 100.0,
+            tolerance.max_failing_fraction * 100.0,
+            diff.max_channel_difference,
+            diff.max_perceptual_difference
+        );
+        println!("     see {} and {}", actual_path.display(), diff_path.display());
+
+        Ok(false)
+    }
+}
+
+/*
+ * Renders every case of the manifest (or only the one called name) and
+ * compares it with its reference, with update the references are rewritten
+ * instead. Cases without a reference image fail. Returns whether every case
+ * passed.
+ */
+pub fn run_golden_tests(manifest_path: &str, name: Option<&str>, update: bool) -> Result<bool, String> {
+    let text = match read_to_string(manifest_path) {
+        Ok(text) => text,
+        Err(e) => return Err(format!("Failed to read golden manifest {}: {}", manifest_path, e)),
+    };
+
+    let golden_file: GoldenFile = match toml::from_str(&text) {
+        Ok(golden_file) => golden_file,
+        Err(e) => {
+            let line = text[..e.span().map_or(0, |span| span.start).min(text.len())]
+                .matches('\n')
+                .count()
+                + 1;
+            return Err(format!("{}:{}: {}", manifest_path, line, e.message()));
+        }
+    };
+
+    let cases: Vec<&GoldenCase> = golden_file
+        .cases
+        .iter()
+        .filter(|case| name.map_or(true, |name| case.name == name))
+        .collect();
+
+    if cases.is_empty() {
+        return Err(match name {
+            Some(name) => format!("no golden case called '{}' in {}", name, manifest_path),
+            None => format!("no golden cases in {}", manifest_path),
+        });
+    }
+
+    let mut failed = 0;
+    for case in cases.iter() {
+        match case.run(update) {
+            Ok(true) => {}
+            Ok(false) => failed += 1,
+            Err(e) => {
+                println!("FAIL {}: {}", case.name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("{} of {} golden cases passed", cases.len() - failed, cases.len());
+
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // width x height of one opaque color
+    fn solid(width: u32, height: u32, pixel: [u8; 4]) -> Vec<u8> {
+        pixel.repeat(width as usize * height as usize)
+    }
+
+    #[test]
+    fn identical_images_pass() {
+        let image = solid(4, 3, [10, 120, 200, 255]);
+        let tolerance = Tolerance::default();
+        let diff = compare_images(&image, &image, 4, 3, &tolerance).unwrap();
+
+        assert_eq!(diff.total_pixels, 12);
+        assert_eq!(diff.failing_pixels, 0);
+        assert_eq!(diff.tolerated_pixels, 0);
+        assert_eq!(diff.max_channel_difference, 0);
+        assert!(diff.passes(&tolerance));
+        assert_eq!(diff.diff_image.len(), image.len());
+    }
+
+    #[test]
+    fn single_changed_pixel_stays_within_tolerance() {
+        let reference = solid(40, 30, [128, 128, 128, 255]);
+        let mut actual = reference.clone();
+        // Small enough for the channel tolerance
+        actual[0] = 133;
+        // Off completely, but only one pixel of 1200
+        actual[4..8].copy_from_slice(&[255, 0, 0, 255]);
+
+        let tolerance = Tolerance {
+            max_failing_fraction: 0.001,
+            ..Tolerance::default()
+        };
+        let diff = compare_images(&reference, &actual, 40, 30, &tolerance).unwrap();
+
+        assert_eq!(diff.tolerated_pixels, 1);
+        assert_eq!(diff.failing_pixels, 1);
+        assert!(diff.passes(&tolerance));
+        assert_eq!(&diff.diff_image[0..4], &[255, 210, 0, 255]);
+        assert_eq!(&diff.diff_image[4..8], &[255, 0, 0, 255]);
+
+        let strict = Tolerance {
+            max_failing_fraction: 0.0,
+            ..Tolerance::default()
+        };
+        assert!(!diff.passes(&strict));
+    }
+
+    #[test]
+    fn size_mismatch_is_an_error() {
+        let reference = solid(4, 4, [0, 0, 0, 255]);
+        let actual = solid(4, 3, [0, 0, 0, 255]);
+
+        assert!(compare_images(&reference, &actual, 4, 4, &Tolerance::default()).is_err());
+        assert!(compare_images(&reference, &reference, 4, 3, &Tolerance::default()).is_err());
+    }
+
+    // Fails before anything is rendered, so no GL is needed
+    #[test]
+    fn missing_reference_fails() {
+        let manifest = std::env::temp_dir().join(format!("golden_{}.toml", std::process::id()));
+        std::fs::write(
+            &manifest,
+            "[[case]]\nname = \"no_such_reference\"\nscene = \"res/golden/cube.toml\"\ncamera_position = [1.0, 1.0, 1.0]\ncamera_target = [0.0, 0.0, 0.0]\n",
+        )
+        .unwrap();
+        let passed = run_golden_tests(manifest.to_str().unwrap(), None, false);
+        let _ = std::fs::remove_file(&manifest);
+
+        assert_eq!(passed, Ok(false));
+    }
+
+    #[test]
+    fn perceptual_difference_range() {
+        let black = [0, 0, 0, 255];
+        let white = [255, 255, 255, 255];
+
+        assert_eq!(perceptual_difference(&black, &black), 0.0);
+        assert!((perceptual_difference(&black, &white) - 0.933).abs() < 0.001);
+        // Symmetric, and a dark gray is closer to black than white is
+        let gray = [60, 60, 60, 255];
+        assert_eq!(perceptual_difference(&black, &gray), perceptual_difference(&gray, &black));
+        assert!(perceptual_difference(&black, &gray) < perceptual_difference(&black, &white));
+        // Fully transparent pixels look white whatever their color
+        assert_eq!(perceptual_difference(&[255, 0, 0, 0], &white), 0.0);
+    }
+}
@@ -0,0 +1,160 @@
+use glam::*;
+
+use crate::camera::Camera;
+use crate::gl;
+use crate::scene_graph::SceneNode;
+use crate::stats::FrameTimes;
+use crate::text::{SdfStyle, TextManager};
+use crate::text_layout::TextLayout;
+
+// One second of frames at 60 FPS
+const FRAME_TIME_SAMPLES: usize = 60;
+// The graph is at least this tall (30 FPS), taller when a frame took longer
+const GRAPH_CEILING: f32 = 1.0 / 30.0;
+const HUD_SCALE: f32 = 0.35;
+const HUD_MARGIN: f32 = 10.0;
+const TEXT_COLOR: Vec3 = Vec3::new(0.9, 0.9, 0.9);
+const GRAPH_COLOR: Vec3 = Vec3::new(0.4, 0.9, 0.3);
+
+// What the HUD shows about the frame besides its timing
+pub struct HudView<'a> {
+    pub camera: &'a Camera,
+    pub draw_calls: u32,
+    // The node picked with select_next, if any
+    pub selected: Option<&'a SceneNode>,
+}
+
+/*
+ * Diagnostics drawn over the top left corner of the window: frame rate, a
+ * graph of the last frame times, where the camera is and how it's set up,
+ * how many draw calls the frame took and the transform of the selected node.
+ */
+pub struct DebugHud {
+    pub visible: bool,
+    frame_times: FrameTimes,
+}
+
+impl DebugHud {
+    pub fn new() -> DebugHud {
+        DebugHud {
+            visible: false,
+            frame_times: FrameTimes::new(FRAME_TIME_SAMPLES),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    // Every frame, also while hidden so the graph is full when it's shown
+    pub fn record_frame(&mut self, frame_time: f32) {
+        self.frame_times.push(frame_time);
+    }
+
+    fn graph_ceiling(&self) -> f32 {
+        self.frame_times.max().max(GRAPH_CEILING)
+    }
+
+    // The lines above and below the graph, which goes on the second line
+    fn lines(&self, view: &HudView) -> (String, String) {
+        let frame_times = &self.frame_times;
+        let header = format!(
+            "FPS {:.1}  {:.1} ms avg  {:.1} ms max",
+            frame_times.fps(),
+            frame_times.average() * 1000.0,
+            frame_times.max() * 1000.0
+        );
+
+        let camera = view.camera;
+        let position = camera.eye();
+        let mut details = format!(
+            "camera {:?}  {:.2} {:.2} {:.2}\nyaw {:.1}  pitch {:.1}  fov {:.1}\ndraw calls {}",
+            camera.mode(),
+            position.x,
+            position.y,
+            position.z,
+            camera.yaw(),
+            camera.pitch(),
+            camera.fov(),
+            view.draw_calls
+        );
+
+        match view.selected {
+            Some(node) => {
+                let transform = &node.transform;
+                let (x, y, z) = transform.rotation.to_euler(EulerRot::XYZ);
+                details += &format!(
+                    "\nselected {}\ntranslation {:.2} {:.2} {:.2}\nrotation {:.1} {:.1} {:.1}\nscale {:.2} {:.2} {:.2}",
+                    if node.name.is_empty() { "(unnamed)" } else { &node.name },
+                    transform.translation.x,
+                    transform.translation.y,
+                    transform.translation.z,
+                    x.to_degrees(),
+                    y.to_degrees(),
+                    z.to_degrees(),
+                    transform.scale.x,
+                    transform.scale.y,
+                    transform.scale.z
+                );
+            }
+            None => details += "\nnothing selected",
+        }
+
+        (header, details)
+    }
+
+    // The text program has to be in use
+    pub fn render(&self, text_manager: &mut TextManager, view: &HudView) {
+        if !self.visible {
+            return;
+        }
+
+        let font = match text_manager.default_font() {
+            Some(font) => font,
+            None => return,
+        };
+
+        let (header, details) = self.lines(view);
+        let graph = format!(
+            "{} {:.0} ms",
+            self.frame_times.graph(self.graph_ceiling()),
+            self.graph_ceiling() * 1000.0
+        );
+
+        let layout = TextLayout::default();
+        let line_height = text_manager.line_height(font) * HUD_SCALE;
+        let x = HUD_MARGIN;
+        let top = text_manager.screen_size().y - HUD_MARGIN - line_height;
+
+        // A shadow keeps it readable over bright parts of the scene
+        let style = text_manager.sdf_style();
+        text_manager.set_sdf_style(SdfStyle {
+            shadow_color: Vec4::new(0.0, 0.0, 0.0, 0.8),
+            shadow_offset: Vec2::new(2.0, 2.0),
+            shadow_softness: 1.0,
+            ..SdfStyle::default()
+        });
+
+        // Over whatever is close to the camera, and filled in wireframe mode too
+        let mut polygon_mode = [gl::FILL as gl::types::GLint; 2];
+        let depth_test = unsafe {
+            gl::GetIntegerv(gl::POLYGON_MODE, polygon_mode.as_mut_ptr());
+            let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+            gl::Disable(gl::DEPTH_TEST);
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+            depth_test
+        };
+
+        text_manager.render_text_with_font(font, &header, x, top, HUD_SCALE, &layout, TEXT_COLOR);
+        text_manager.render_text_with_font(font, &graph, x, top - line_height, HUD_SCALE, &layout, GRAPH_COLOR);
+        text_manager.render_text_with_font(font, &details, x, top - 2.0 * line_height, HUD_SCALE, &layout, TEXT_COLOR);
+
+        unsafe {
+            if depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            }
+            gl::PolygonMode(gl::FRONT_AND_BACK, polygon_mode[0] as gl::types::GLenum);
+        }
+        text_manager.set_sdf_style(style);
+    }
+}
@@ -0,0 +1,602 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::read_to_string;
+
+use glfw::{Action, GamepadAxis, GamepadButton, Key, MouseButton, WindowEvent};
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::gamepad::GamepadSettings;
+
+pub const INPUT_CONFIG: &str = "res/input.toml";
+
+/*
+ * Every action the render loop asks about with its default bindings, the
+ * names are the ones the config file uses, see parse_binding(). Held actions
+ * move things for as long as they're down, the rest fire once when pressed.
+ * Movement, look and zoom go by value() so sticks and triggers move them
+ * as far as they're pushed.
+ */
+pub const ACTIONS: &[(&str, &[&str])] = &[
+    ("quit", &["Escape"]),
+    ("move_forward", &["W", "GamepadLeftY-"]),
+    ("move_backward", &["S", "GamepadLeftY+"]),
+    ("move_left", &["A", "GamepadLeftX-"]),
+    ("move_right", &["D", "GamepadLeftX+"]),
+    ("move_up", &["E", "GamepadRightBumper"]),
+    ("move_down", &["Q", "GamepadLeftBumper"]),
+    ("look_left", &["GamepadRightX-"]),
+    ("look_right", &["GamepadRightX+"]),
+    ("look_up", &["GamepadRightY-"]),
+    ("look_down", &["GamepadRightY+"]),
+    ("zoom_in", &["KpAdd", "GamepadRightTrigger"]),
+    ("zoom_out", &["KpSubtract", "GamepadLeftTrigger"]),
+    ("reset_zoom", &["KpMultiply", "GamepadRightThumb"]),
+    ("cycle_camera_mode", &["V", "GamepadY"]),
+    ("cube_up", &["Up"]),
+    ("cube_down", &["Down"]),
+    ("cube_left", &["Left"]),
+    ("cube_right", &["Right"]),
+    ("cube_in", &["C"]),
+    ("cube_out", &["Z"]),
+    ("rotate_x_cw", &["I"]),
+    ("rotate_x_ccw", &["U"]),
+    ("rotate_y_cw", &["K"]),
+    ("rotate_y_ccw", &["J"]),
+    ("rotate_z_cw", &["M"]),
+    ("rotate_z_ccw", &["N"]),
+    ("reset_rotation", &["T"]),
+    ("mix_more", &["Num3"]),
+    ("mix_less", &["Num1"]),
+    ("light_forward", &["Kp8"]),
+    ("light_backward", &["Kp2"]),
+    ("light_left", &["Kp4"]),
+    ("light_right", &["Kp6"]),
+    ("light_up", &["Kp9"]),
+    ("light_down", &["Kp3"]),
+    ("toggle_wireframe", &["F"]),
+    ("toggle_grayscale", &["G", "GamepadX"]),
+    ("toggle_recording", &["F10"]),
+    ("screenshot", &["F12", "GamepadBack"]),
+    ("toggle_hud", &["F3", "GamepadStart"]),
+    ("select_next", &["Tab"]),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum Binding {
+    Key(Key),
+    MouseButton(MouseButton),
+    GamepadButton(GamepadButton),
+    // One way of a stick axis, triggers only go Positive
+    GamepadAxis(GamepadAxis, AxisDirection),
+}
+
+// glfw has no way to go from a name to a Key
+const KEYS: &[Key] = &[
+    Key::Space, Key::Apostrophe, Key::Comma, Key::Minus, Key::Period, Key::Slash, Key::Num0,
+    Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8,
+    Key::Num9, Key::Semicolon, Key::Equal, Key::A, Key::B, Key::C, Key::D, Key::E, Key::F,
+    Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q,
+    Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z, Key::LeftBracket,
+    Key::Backslash, Key::RightBracket, Key::GraveAccent, Key::World1, Key::World2, Key::Escape,
+    Key::Enter, Key::Tab, Key::Backspace, Key::Insert, Key::Delete, Key::Right, Key::Left,
+    Key::Down, Key::Up, Key::PageUp, Key::PageDown, Key::Home, Key::End, Key::CapsLock,
+    Key::ScrollLock, Key::NumLock, Key::PrintScreen, Key::Pause, Key::F1, Key::F2, Key::F3,
+    Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+    Key::F13, Key::F14, Key::F15, Key::F16, Key::F17, Key::F18, Key::F19, Key::F20, Key::F21,
+    Key::F22, Key::F23, Key::F24, Key::F25, Key::Kp0, Key::Kp1, Key::Kp2, Key::Kp3, Key::Kp4,
+    Key::Kp5, Key::Kp6, Key::Kp7, Key::Kp8, Key::Kp9, Key::KpDecimal, Key::KpDivide,
+    Key::KpMultiply, Key::KpSubtract, Key::KpAdd, Key::KpEnter, Key::KpEqual, Key::LeftShift,
+    Key::LeftControl, Key::LeftAlt, Key::LeftSuper, Key::RightShift, Key::RightControl,
+    Key::RightAlt, Key::RightSuper, Key::Menu,
+];
+
+/*
+ * Keys go by their glfw name ("W", "Kp8", "F12", "LeftShift"), mouse buttons
+ * are MouseLeft, MouseRight, MouseMiddle and Mouse4 to Mouse8, gamepad buttons
+ * are Gamepad followed by the glfw name without Button ("GamepadA",
+ * "GamepadDpadUp", "GamepadLeftBumper"). Sticks are GamepadLeftX, GamepadLeftY,
+ * GamepadRightX and GamepadRightY with + or - for the direction (Y- is up),
+ * triggers GamepadLeftTrigger and GamepadRightTrigger.
+ */
+pub fn parse_binding(name: &str) -> Option<Binding> {
+    if let Some(key) = KEYS.iter().find(|key| format!("{:?}", key) == name) {
+        return Some(Binding::Key(*key));
+    }
+
+    if let Some(button) = name.strip_prefix("Mouse") {
+        let index = match button {
+            "Left" => 1,
+            "Right" => 2,
+            "Middle" => 3,
+            number => number.parse::<i32>().ok().filter(|number| *number >= 4)?,
+        };
+        return MouseButton::from_i32(index - 1).map(Binding::MouseButton);
+    }
+
+    if let Some(button) = name.strip_prefix("Gamepad") {
+        let (axis_name, direction) = match button.strip_suffix('+') {
+            Some(axis_name) => (axis_name, AxisDirection::Positive),
+            None => match button.strip_suffix('-') {
+                Some(axis_name) => (axis_name, AxisDirection::Negative),
+                None => (button, AxisDirection::Positive),
+            },
+        };
+        if let Some(axis) = (0..=glfw::ffi::GAMEPAD_AXIS_LAST)
+            .filter_map(GamepadAxis::from_i32)
+            .find(|axis| format!("{:?}", axis) == format!("Axis{}", axis_name))
+        {
+            // Triggers have no direction, sticks need one
+            let is_trigger = axis_name.ends_with("Trigger");
+            if is_trigger != (axis_name == button) {
+                return None;
+            }
+            return Some(Binding::GamepadAxis(axis, direction));
+        }
+
+        return (0..=glfw::ffi::GAMEPAD_BUTTON_LAST)
+            .filter_map(GamepadButton::from_i32)
+            .find(|gamepad_button| format!("{:?}", gamepad_button) == format!("Button{}", button))
+            .map(Binding::GamepadButton);
+    }
+
+    None
+}
+
+// The other way around, for messages
+pub fn binding_name(binding: &Binding) -> String {
+    match binding {
+        Binding::Key(key) => format!("{:?}", key),
+        Binding::MouseButton(MouseButton::Button1) => "MouseLeft".to_string(),
+        Binding::MouseButton(MouseButton::Button2) => "MouseRight".to_string(),
+        Binding::MouseButton(MouseButton::Button3) => "MouseMiddle".to_string(),
+        Binding::MouseButton(button) => format!("Mouse{}", *button as i32 + 1),
+        Binding::GamepadButton(button) => {
+            format!("Gamepad{}", format!("{:?}", button).trim_start_matches("Button"))
+        }
+        Binding::GamepadAxis(axis, direction) => {
+            let axis_name = format!("{:?}", axis);
+            let axis_name = axis_name.trim_start_matches("Axis");
+            match direction {
+                _ if axis_name.ends_with("Trigger") => format!("Gamepad{}", axis_name),
+                AxisDirection::Positive => format!("Gamepad{}+", axis_name),
+                AxisDirection::Negative => format!("Gamepad{}-", axis_name),
+            }
+        }
+    }
+}
+
+/*
+ * Config file layout, every line replaces the default bindings of one action,
+ * the optional [gamepad] table sets GamepadSettings:
+ *
+ * move_forward = "W"
+ * screenshot = ["F12", "GamepadBack"]
+ * toggle_wireframe = []            unbound
+ *
+ * [gamepad]
+ * deadzone = 0.15
+ * curve = 2.0
+ * trigger_deadzone = 0.05
+ */
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GamepadEntry {
+    deadzone: Option<f32>,
+    curve: Option<f32>,
+    trigger_deadzone: Option<f32>,
+}
+
+impl GamepadEntry {
+    fn settings(&self) -> Result<GamepadSettings, String> {
+        let default = GamepadSettings::default();
+        let settings = GamepadSettings {
+            deadzone: self.deadzone.unwrap_or(default.deadzone),
+            curve: self.curve.unwrap_or(default.curve),
+            trigger_deadzone: self.trigger_deadzone.unwrap_or(default.trigger_deadzone),
+        };
+
+        if !(0.0..1.0).contains(&settings.deadzone) {
+            return Err(format!("deadzone has to be at least 0 and below 1, not {}", settings.deadzone));
+        }
+        if !(0.0..1.0).contains(&settings.trigger_deadzone) {
+            return Err(format!(
+                "trigger_deadzone has to be at least 0 and below 1, not {}",
+                settings.trigger_deadzone
+            ));
+        }
+        if !(settings.curve > 0.0) {
+            return Err(format!("curve has to be above 0, not {}", settings.curve));
+        }
+
+        Ok(settings)
+    }
+}
+
+/*
+ * Named actions bound to keys, mouse buttons and gamepad buttons. Feed it
+ * every window event between begin_frame() calls, then ask held() for what's
+ * down and pressed()/released() for what changed since the last begin_frame().
+ * Sticks and triggers also count as held once they're pushed halfway.
+ */
+pub struct InputMap {
+    bindings: BTreeMap<String, Vec<Binding>>,
+    down: HashSet<Binding>,
+    pressed: HashSet<Binding>,
+    released: HashSet<Binding>,
+    // 0 to 1 for sticks and triggers
+    analog: HashMap<Binding, f32>,
+    gamepad: GamepadSettings,
+}
+
+impl InputMap {
+    // Every action with its default bindings
+    pub fn new() -> InputMap {
+        let mut bindings = BTreeMap::new();
+        for (action, names) in ACTIONS.iter() {
+            let action_bindings = names
+                .iter()
+                .map(|name| parse_binding(name).expect("invalid default binding"))
+                .collect();
+            bindings.insert(action.to_string(), action_bindings);
+        }
+
+        InputMap {
+            bindings: bindings,
+            down: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            analog: HashMap::new(),
+            gamepad: GamepadSettings::default(),
+        }
+    }
+
+    // Defaults with the actions the file lists rebound
+    pub fn load(path: &str) -> Result<InputMap, String> {
+        let text = match read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => return Err(format!("Failed to read input config {}: {}", path, e)),
+        };
+
+        let error_at = |offset: usize, message: String| {
+            let line = text[..offset.min(text.len())].matches('\n').count() + 1;
+            format!("{}:{}: {}", path, line, message)
+        };
+
+        let entries: HashMap<Spanned<String>, toml::Value> = match toml::from_str(&text) {
+            Ok(entries) => entries,
+            Err(e) => return Err(error_at(e.span().map_or(0, |span| span.start), e.message().to_string())),
+        };
+
+        let mut input_map = InputMap::new();
+
+        // In file order so the first mistake is the one reported
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        entries.sort_by_key(|(action, _)| action.span().start);
+
+        for (action, value) in entries.into_iter() {
+            if action.get_ref() == "gamepad" {
+                input_map.gamepad = match value.try_into::<GamepadEntry>() {
+                    Ok(entry) => entry.settings(),
+                    Err(e) => Err(e.message().to_string()),
+                }
+                .map_err(|message| error_at(action.span().start, format!("[gamepad]: {}", message)))?;
+                continue;
+            }
+
+            if !input_map.bindings.contains_key(action.get_ref()) {
+                return Err(error_at(
+                    action.span().start,
+                    format!("unknown action '{}'", action.get_ref()),
+                ));
+            }
+
+            let names = match value {
+                toml::Value::String(name) => vec![toml::Value::String(name)],
+                toml::Value::Array(names) => names,
+                _ => {
+                    return Err(error_at(
+                        action.span().start,
+                        format!("'{}' needs a key or button name, or a list of them", action.get_ref()),
+                    ))
+                }
+            };
+
+            let mut bindings = Vec::new();
+            for name in names.iter() {
+                match name.as_str().and_then(parse_binding) {
+                    Some(binding) => bindings.push(binding),
+                    None => {
+                        return Err(error_at(
+                            action.span().start,
+                            format!("unknown key or button {} for '{}'", name, action.get_ref()),
+                        ))
+                    }
+                }
+            }
+
+            input_map
+                .bindings
+                .insert(action.get_ref().clone(), bindings);
+        }
+
+        if let Some(conflict) = input_map.conflicts().first() {
+            return Err(format!("{}: {}", path, conflict));
+        }
+
+        Ok(input_map)
+    }
+
+    pub fn gamepad_settings(&self) -> &GamepadSettings {
+        &self.gamepad
+    }
+
+    pub fn set_gamepad_settings(&mut self, settings: GamepadSettings) {
+        self.gamepad = settings;
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        match self.bindings.get(action) {
+            Some(bindings) => bindings,
+            None => &[],
+        }
+    }
+
+    // Replaces what action is bound to, refused when one of the bindings
+    // already belongs to another action
+    pub fn rebind(&mut self, action: &str, bindings: &[Binding]) -> Result<(), String> {
+        if !self.bindings.contains_key(action) {
+            return Err(format!("unknown action '{}'", action));
+        }
+
+        for binding in bindings.iter() {
+            if let Some(other) = self
+                .bindings
+                .iter()
+                .find(|(other, other_bindings)| other.as_str() != action && other_bindings.contains(binding))
+            {
+                return Err(format!(
+                    "{} is already bound to '{}'",
+                    binding_name(binding),
+                    other.0
+                ));
+            }
+        }
+
+        self.bindings.insert(action.to_string(), bindings.to_vec());
+        Ok(())
+    }
+
+    // One message per key or button bound to more than one action
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut actions_by_binding: BTreeMap<Binding, Vec<&str>> = BTreeMap::new();
+        for (action, bindings) in self.bindings.iter() {
+            for binding in bindings.iter() {
+                actions_by_binding.entry(*binding).or_default().push(action);
+            }
+        }
+
+        actions_by_binding
+            .iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .map(|(binding, actions)| {
+                format!("{} is bound to both '{}'", binding_name(binding), actions.join("' and '"))
+            })
+            .collect()
+    }
+
+    // Forgets what was pressed and released during the last frame
+    pub fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    // Key and mouse button events, anything else is ignored
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        let (binding, action) = match event {
+            WindowEvent::Key(key, _, action, _) => (Binding::Key(*key), *action),
+            WindowEvent::MouseButton(button, action, _) => (Binding::MouseButton(*button), *action),
+            _ => return,
+        };
+
+        match action {
+            Action::Press => self.set_down(binding, true),
+            Action::Release => self.set_down(binding, false),
+            Action::Repeat => {}
+        }
+    }
+
+    // For inputs that are polled rather than sent as events, like gamepads
+    pub fn set_down(&mut self, binding: Binding, down: bool) {
+        if down {
+            if self.down.insert(binding) {
+                self.pressed.insert(binding);
+            }
+        } else if self.down.remove(&binding) {
+            self.released.insert(binding);
+        }
+    }
+
+    // value is how far a stick or trigger is pushed, 0 to 1, it's held
+    // from halfway on
+    pub fn set_analog(&mut self, binding: Binding, value: f32) {
+        self.analog.insert(binding, value);
+        self.set_down(binding, value >= 0.5);
+    }
+
+    // How far the action's sticks or triggers are pushed, 1 while one of
+    // its keys or buttons is down
+    pub fn value(&self, action: &str) -> f32 {
+        self.bindings(action)
+            .iter()
+            .map(|binding| match self.analog.get(binding) {
+                Some(value) => *value,
+                None if self.down.contains(binding) => 1.0,
+                None => 0.0,
+            })
+            .fold(0.0, f32::max)
+    }
+
+    pub fn held(&self, action: &str) -> bool {
+        self.bindings(action).iter().any(|binding| self.down.contains(binding))
+    }
+
+    pub fn pressed(&self, action: &str) -> bool {
+        self.bindings(action).iter().any(|binding| self.pressed.contains(binding))
+    }
+
+    pub fn released(&self, action: &str) -> bool {
+        self.bindings(action).iter().any(|binding| self.released.contains(binding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT_TRIGGER: Binding = Binding::GamepadAxis(GamepadAxis::AxisLeftTrigger, AxisDirection::Positive);
+
+    // Loads text as if it was an input config file
+    fn load(name: &str, text: &str) -> Result<InputMap, String> {
+        let path = std::env::temp_dir().join(format!("input_{}_{}.toml", name, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let input_map = InputMap::load(path.to_str().unwrap());
+        let _ = std::fs::remove_file(&path);
+        input_map
+    }
+
+    #[test]
+    fn binding_names() {
+        assert_eq!(parse_binding("W"), Some(Binding::Key(Key::W)));
+        assert_eq!(parse_binding("MouseLeft"), Some(Binding::MouseButton(MouseButton::Button1)));
+        assert_eq!(parse_binding("Mouse5"), Some(Binding::MouseButton(MouseButton::Button5)));
+        assert_eq!(parse_binding("GamepadA"), Some(Binding::GamepadButton(GamepadButton::ButtonA)));
+        assert_eq!(
+            parse_binding("GamepadLeftY-"),
+            Some(Binding::GamepadAxis(GamepadAxis::AxisLeftY, AxisDirection::Negative))
+        );
+        assert_eq!(parse_binding("GamepadLeftTrigger"), Some(LEFT_TRIGGER));
+
+        // Every default goes back to the name it came from
+        for (_, names) in ACTIONS.iter() {
+            for name in names.iter() {
+                assert_eq!(binding_name(&parse_binding(name).unwrap()), *name);
+            }
+        }
+    }
+
+    #[test]
+    fn bad_binding_names() {
+        assert_eq!(parse_binding("w"), None);
+        assert_eq!(parse_binding("Mouse3"), None);
+        assert_eq!(parse_binding("Mouse9"), None);
+        assert_eq!(parse_binding("GamepadZ"), None);
+        // Sticks need a direction, triggers can't have one
+        assert_eq!(parse_binding("GamepadLeftX"), None);
+        assert_eq!(parse_binding("GamepadRightTrigger+"), None);
+        assert_eq!(parse_binding("GamepadRightTrigger-"), None);
+    }
+
+    #[test]
+    fn load_rebinds_listed_actions() {
+        let input_map = load("rebind", "quit = \"Q\"\nmove_down = [\"LeftShift\", \"GamepadB\"]\nscreenshot = []\n").unwrap();
+        assert_eq!(input_map.bindings("quit"), &[Binding::Key(Key::Q)]);
+        assert_eq!(
+            input_map.bindings("move_down"),
+            &[Binding::Key(Key::LeftShift), Binding::GamepadButton(GamepadButton::ButtonB)]
+        );
+        assert!(input_map.bindings("screenshot").is_empty());
+        // Left out, so the default
+        assert_eq!(input_map.bindings("move_up"), InputMap::new().bindings("move_up"));
+    }
+
+    #[test]
+    fn load_errors() {
+        let error = |name: &str, text: &str| load(name, text).err().expect(text);
+
+        let unknown_action = error("action", "quit = \"Escape\"\njump = \"Space\"\n");
+        assert!(unknown_action.ends_with(":2: unknown action 'jump'"), "{}", unknown_action);
+
+        let unknown_key = error("key", "quit = \"Esc\"\n");
+        assert!(unknown_key.contains(":1: unknown key or button \"Esc\" for 'quit'"), "{}", unknown_key);
+
+        let wrong_type = error("type", "quit = 27\n");
+        assert!(wrong_type.contains("'quit' needs a key or button name"), "{}", wrong_type);
+        let wrong_type = error("list_type", "quit = [\"Escape\", 27]\n");
+        assert!(wrong_type.contains("unknown key or button 27"), "{}", wrong_type);
+
+        let no_direction = error("stick", "look_left = \"GamepadRightX\"\n");
+        assert!(no_direction.contains("GamepadRightX"), "{}", no_direction);
+
+        let trigger_direction = error("trigger", "zoom_in = \"GamepadRightTrigger+\"\n");
+        assert!(trigger_direction.contains("GamepadRightTrigger+"), "{}", trigger_direction);
+
+        let conflict = error("conflict", "quit = \"W\"\n");
+        assert!(conflict.contains("W is bound to both 'move_forward' and 'quit'"), "{}", conflict);
+
+        let gamepad = error("gamepad", "[gamepad]\ndeadzone = 1.5\n");
+        assert!(gamepad.contains("[gamepad]: deadzone"), "{}", gamepad);
+
+        assert!(InputMap::load("res/missing.toml").is_err());
+    }
+
+    #[test]
+    fn conflicts_and_rebind() {
+        let mut input_map = InputMap::new();
+        assert!(input_map.conflicts().is_empty());
+
+        assert!(input_map.rebind("quit", &[Binding::Key(Key::W)]).is_err());
+        assert!(input_map.rebind("jump", &[Binding::Key(Key::Space)]).is_err());
+        assert!(input_map.rebind("quit", &[Binding::Key(Key::Space)]).is_ok());
+        assert_eq!(input_map.bindings("quit"), &[Binding::Key(Key::Space)]);
+
+        input_map.bindings.insert("cube_up".to_string(), vec![Binding::Key(Key::Space)]);
+        assert_eq!(input_map.conflicts(), vec!["Space is bound to both 'cube_up' and 'quit'".to_string()]);
+    }
+
+    #[test]
+    fn shipped_config_loads() {
+        let input_map = InputMap::load(INPUT_CONFIG).unwrap();
+        for (action, _) in ACTIONS.iter() {
+            assert_eq!(input_map.bindings(action), InputMap::new().bindings(action), "{}", action);
+        }
+        assert_eq!(input_map.gamepad_settings().deadzone, GamepadSettings::default().deadzone);
+    }
+
+    #[test]
+    fn analog_value_is_kept_past_halfway() {
+        let mut input = InputMap::new();
+
+        input.set_analog(LEFT_TRIGGER, 0.3);
+        assert_eq!(input.value("zoom_out"), 0.3);
+        assert!(!input.held("zoom_out"));
+
+        input.set_analog(LEFT_TRIGGER, 0.7);
+        assert_eq!(input.value("zoom_out"), 0.7);
+        assert!(input.held("zoom_out"));
+        assert!(input.pressed("zoom_out"));
+
+        input.begin_frame();
+        input.set_analog(LEFT_TRIGGER, 0.2);
+        assert_eq!(input.value("zoom_out"), 0.2);
+        assert!(!input.held("zoom_out"));
+        assert!(input.released("zoom_out"));
+    }
+
+    #[test]
+    fn keys_count_as_fully_pushed() {
+        let mut input = InputMap::new();
+        input.set_analog(LEFT_TRIGGER, 0.6);
+        input.set_down(Binding::Key(Key::KpSubtract), true);
+        assert_eq!(input.value("zoom_out"), 1.0);
+
+        input.set_down(Binding::Key(Key::KpSubtract), false);
+        assert_eq!(input.value("zoom_out"), 0.6);
+        assert_eq!(input.value("zoom_in"), 0.0);
+    }
+}
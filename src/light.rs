@@ -0,0 +1,139 @@
+use glam::*;
+
+use crate::program::Program;
+
+// A point or spot light reaches the fragment with
+// 1.0 / (constant + linear * d + quadratic * d * d) of its strength
+#[derive(Clone, Copy)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    pub fn new(constant: f32, linear: f32, quadratic: f32) -> Attenuation {
+        Attenuation {
+            constant: constant,
+            linear: linear,
+            quadratic: quadratic,
+        }
+    }
+
+    // No falloff at all
+    pub fn none() -> Attenuation {
+        Attenuation::new(1.0, 0.0, 0.0)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum LightKind {
+    Directional {
+        direction: Vec3,
+    },
+    Point {
+        position: Vec3,
+        attenuation: Attenuation,
+    },
+    // Full strength inside inner_angle, fading out up to outer_angle (degrees)
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        inner_angle: f32,
+        outer_angle: f32,
+        attenuation: Attenuation,
+    },
+}
+
+#[derive(Clone, Copy)]
+pub struct Light {
+    pub kind: LightKind,
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    // Only directional and spot lights can, see shadow.rs
+    pub cast_shadows: bool,
+}
+
+impl Light {
+    pub fn new(kind: LightKind, ambient: Vec3, diffuse: Vec3, specular: Vec3) -> Light {
+        Light {
+            kind: kind,
+            ambient: ambient,
+            diffuse: diffuse,
+            specular: specular,
+            cast_shadows: false,
+        }
+    }
+
+    // A light of the given color with a dim ambient term
+    pub fn with_color(kind: LightKind, color: Vec3) -> Light {
+        Light::new(kind, color * 0.1, color, color)
+    }
+
+    // Directional lights have no position
+    pub fn position(&self) -> Option<Vec3> {
+        match self.kind {
+            LightKind::Directional { .. } => None,
+            LightKind::Point { position, .. } => Some(position),
+            LightKind::Spot { position, .. } => Some(position),
+        }
+    }
+
+    pub fn position_mut(&mut self) -> Option<&mut Vec3> {
+        match &mut self.kind {
+            LightKind::Directional { .. } => None,
+            LightKind::Point { position, .. } => Some(position),
+            LightKind::Spot { position, .. } => Some(position),
+        }
+    }
+}
+
+/*
+ * Surface response of the lit shader, the ambient and diffuse colors are
+ * multiplied with the sampled texture color.
+ *
+ * uniform struct Material
+ * {
+ *     vec3 ambient;
+ *     vec3 diffuse;
+ *     vec3 specular;
+ *     float shininess;
+ * } material;
+ */
+#[derive(Clone, Copy)]
+pub struct Material {
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+}
+
+impl Material {
+    pub fn new(ambient: Vec3, diffuse: Vec3, specular: Vec3, shininess: f32) -> Material {
+        Material {
+            ambient: ambient,
+            diffuse: diffuse,
+            specular: specular,
+            shininess: shininess,
+        }
+    }
+
+    // Programs without a material uniform are left alone, the program has to be in use
+    pub fn upload(&self, program: &Program) {
+        if !program.has_uniform("material.shininess") {
+            return;
+        }
+
+        program.set_uniform("material.ambient", self.ambient);
+        program.set_uniform("material.diffuse", self.diffuse);
+        program.set_uniform("material.specular", self.specular);
+        program.set_uniform("material.shininess", self.shininess);
+    }
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material::new(Vec3::ONE, Vec3::ONE, Vec3::splat(0.5), 32.0)
+    }
+}
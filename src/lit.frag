@@ -0,0 +1,154 @@
+#version 330 core
+
+// Has to match MAX_LIGHTS and the light kinds in uniform_block.rs
+#define MAX_LIGHTS 8
+#define DIRECTIONAL_LIGHT 0
+#define POINT_LIGHT 1
+#define SPOT_LIGHT 2
+
+struct Light
+{
+    vec3 position;
+    int kind;
+    vec3 direction;
+    float inner_cutoff;
+    vec3 ambient;
+    float outer_cutoff;
+    vec3 diffuse;
+    float constant;
+    vec3 specular;
+    float linear;
+    float quadratic;
+};
+
+layout (std140) uniform Lights
+{
+    int light_count;
+    Light lights[MAX_LIGHTS];
+};
+
+layout (std140) uniform Camera
+{
+    mat4 projection;
+    mat4 look_at;
+    vec3 camera_position;
+};
+
+struct Material
+{
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+    float shininess;
+};
+
+in vec3 FragPos;
+in vec3 Normal;
+in vec2 TexCoord;
+in vec4 FragPosLightSpace;
+
+uniform Material material;
+uniform sampler2D texture1;
+uniform sampler2D texture2;
+uniform float mixvalue = 0.0;
+// Phong when false
+uniform bool blinn_phong = true;
+
+// Set by the ShadowMap, see shadow.rs
+uniform sampler2D shadow_map;
+uniform int shadow_light = -1;
+uniform float shadow_min_bias = 0.0005;
+uniform float shadow_max_bias = 0.005;
+uniform int shadow_pcf_radius = 1;
+
+out vec4 outColor;
+
+// Fraction of the PCF samples around the fragment that are in shadow
+float shadow_amount(vec3 normal, vec3 light_dir)
+{
+    vec3 coords = FragPosLightSpace.xyz / FragPosLightSpace.w * 0.5 + 0.5;
+
+    // Past the far plane of the light
+    if (coords.z > 1.0)
+        return 0.0;
+
+    float bias = max(shadow_max_bias * (1.0 - dot(normal, light_dir)), shadow_min_bias);
+    vec2 texel_size = 1.0 / vec2(textureSize(shadow_map, 0));
+
+    float shadow = 0.0;
+    for (int x = -shadow_pcf_radius; x <= shadow_pcf_radius; x++) {
+        for (int y = -shadow_pcf_radius; y <= shadow_pcf_radius; y++) {
+            float closest_depth = texture(shadow_map, coords.xy + vec2(x, y) * texel_size).r;
+            shadow += coords.z - bias > closest_depth ? 1.0 : 0.0;
+        }
+    }
+
+    float side = float(2 * shadow_pcf_radius + 1);
+    return shadow / (side * side);
+}
+
+vec3 light_contribution(Light light, bool casts_shadow, vec3 normal, vec3 view_dir, vec3 base_color)
+{
+    vec3 light_dir;
+    float attenuation = 1.0;
+    float cone = 1.0;
+
+    if (light.kind == DIRECTIONAL_LIGHT) {
+        light_dir = normalize(-light.direction);
+    } else {
+        float light_distance = length(light.position - FragPos);
+        light_dir = (light.position - FragPos) / light_distance;
+        attenuation = 1.0 / (light.constant + light.linear * light_distance +
+                light.quadratic * light_distance * light_distance);
+
+        if (light.kind == SPOT_LIGHT) {
+            float theta = dot(light_dir, normalize(-light.direction));
+            cone = clamp((theta - light.outer_cutoff) /
+                    (light.inner_cutoff - light.outer_cutoff), 0.0, 1.0);
+        }
+    }
+
+    float diffuse_strength = max(dot(normal, light_dir), 0.0);
+
+    float specular_strength;
+    if (blinn_phong) {
+        vec3 halfway_dir = normalize(light_dir + view_dir);
+        specular_strength = pow(max(dot(normal, halfway_dir), 0.0), material.shininess);
+    } else {
+        vec3 reflect_dir = reflect(-light_dir, normal);
+        specular_strength = pow(max(dot(view_dir, reflect_dir), 0.0), material.shininess);
+    }
+
+    vec3 ambient = light.ambient * material.ambient * base_color;
+    vec3 diffuse = light.diffuse * diffuse_strength * material.diffuse * base_color;
+    vec3 specular = light.specular * specular_strength * material.specular;
+
+    float lit = 1.0;
+    if (casts_shadow)
+        lit = 1.0 - shadow_amount(normal, light_dir);
+
+    return attenuation * (ambient + cone * lit * (diffuse + specular));
+}
+
+void main()
+{
+    vec4 base_color = mix(texture(texture1, TexCoord), texture(texture2, TexCoord), mixvalue);
+
+    // Scenes without lights are drawn unlit
+    if (light_count == 0) {
+        outColor = base_color;
+        return;
+    }
+
+    vec3 view_dir = normalize(camera_position - FragPos);
+    vec3 normal = normalize(Normal);
+    // Quads are seen from both sides, light the side facing the camera
+    if (dot(normal, view_dir) < 0.0)
+        normal = -normal;
+
+    vec3 result = vec3(0.0);
+    for (int i = 0; i < light_count; i++)
+        result += light_contribution(lights[i], i == shadow_light, normal, view_dir, base_color.rgb);
+
+    outColor = vec4(result, base_color.a);
+}
@@ -0,0 +1,31 @@
+#version 330 core
+
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aNormal;
+layout (location = 2) in vec2 aTexCoord;
+
+out vec3 FragPos;
+out vec3 Normal;
+out vec2 TexCoord;
+out vec4 FragPosLightSpace;
+
+layout (std140) uniform Camera
+{
+	mat4 projection;
+	mat4 look_at;
+	vec3 camera_position;
+};
+
+uniform mat4 model;
+// Set by the ShadowMap, see shadow.rs
+uniform mat4 light_space;
+
+void main()
+{
+	FragPos = vec3(model * vec4(aPos, 1.0));
+	// Keeps normals perpendicular under non-uniform scaling
+	Normal = mat3(transpose(inverse(model))) * aNormal;
+	TexCoord = aTexCoord;
+	FragPosLightSpace = light_space * vec4(FragPos, 1.0);
+	gl_Position = projection * look_at * vec4(FragPos, 1.0);
+}
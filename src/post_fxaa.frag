@@ -0,0 +1,57 @@
+#version 330 core
+
+// Simplified FXAA: blends along the edge direction found from the luma
+// of the four diagonal neighbours
+
+#define FXAA_REDUCE_MIN (1.0 / 128.0)
+#define FXAA_REDUCE_MUL (1.0 / 8.0)
+#define FXAA_SPAN_MAX 8.0
+
+in vec2 TexCoord;
+
+uniform sampler2D screen_texture;
+uniform vec2 texel_size;
+
+out vec4 outColor;
+
+void main()
+{
+    vec3 luma_weights = vec3(0.299, 0.587, 0.114);
+
+    vec3 rgb_nw = texture(screen_texture, TexCoord + vec2(-1.0, -1.0) * texel_size).rgb;
+    vec3 rgb_ne = texture(screen_texture, TexCoord + vec2(1.0, -1.0) * texel_size).rgb;
+    vec3 rgb_sw = texture(screen_texture, TexCoord + vec2(-1.0, 1.0) * texel_size).rgb;
+    vec3 rgb_se = texture(screen_texture, TexCoord + vec2(1.0, 1.0) * texel_size).rgb;
+    vec4 color_m = texture(screen_texture, TexCoord);
+
+    float luma_nw = dot(rgb_nw, luma_weights);
+    float luma_ne = dot(rgb_ne, luma_weights);
+    float luma_sw = dot(rgb_sw, luma_weights);
+    float luma_se = dot(rgb_se, luma_weights);
+    float luma_m = dot(color_m.rgb, luma_weights);
+
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    vec2 dir;
+    dir.x = -((luma_nw + luma_ne) - (luma_sw + luma_se));
+    dir.y = ((luma_nw + luma_sw) - (luma_ne + luma_se));
+
+    float dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * (0.25 * FXAA_REDUCE_MUL),
+            FXAA_REDUCE_MIN);
+    float rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) * texel_size;
+
+    vec3 rgb_a = 0.5 * (
+            texture(screen_texture, TexCoord + dir * (1.0 / 3.0 - 0.5)).rgb +
+            texture(screen_texture, TexCoord + dir * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 rgb_b = rgb_a * 0.5 + 0.25 * (
+            texture(screen_texture, TexCoord + dir * -0.5).rgb +
+            texture(screen_texture, TexCoord + dir * 0.5).rgb);
+
+    float luma_b = dot(rgb_b, luma_weights);
+    if (luma_b < luma_min || luma_b > luma_max)
+        outColor = vec4(rgb_a, color_m.a);
+    else
+        outColor = vec4(rgb_b, color_m.a);
+}
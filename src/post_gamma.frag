@@ -0,0 +1,14 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D screen_texture;
+uniform float gamma = 2.2;
+
+out vec4 outColor;
+
+void main()
+{
+    vec4 color = texture(screen_texture, TexCoord);
+    outColor = vec4(pow(color.rgb, vec3(1.0 / gamma)), color.a);
+}
@@ -0,0 +1,14 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D screen_texture;
+
+out vec4 outColor;
+
+void main()
+{
+    vec4 color = texture(screen_texture, TexCoord);
+    float luma = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    outColor = vec4(vec3(luma), color.a);
+}
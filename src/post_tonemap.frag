@@ -0,0 +1,14 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D screen_texture;
+uniform float exposure = 1.0;
+
+out vec4 outColor;
+
+void main()
+{
+    vec4 hdr_color = texture(screen_texture, TexCoord);
+    outColor = vec4(vec3(1.0) - exp(-hdr_color.rgb * exposure), hdr_color.a);
+}
@@ -0,0 +1,17 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D screen_texture;
+uniform float radius = 0.5;
+uniform float strength = 0.5;
+
+out vec4 outColor;
+
+void main()
+{
+    vec4 color = texture(screen_texture, TexCoord);
+    float center_distance = length(TexCoord - vec2(0.5));
+    float darkening = smoothstep(radius, radius + 0.4, center_distance) * strength;
+    outColor = vec4(color.rgb * (1.0 - darkening), color.a);
+}
@@ -0,0 +1,234 @@
+use glam::*;
+
+use crate::framebuffer::{ColorFormat, DepthAttachment, FramebufferDescriptor};
+use crate::gl;
+use crate::program::Program;
+use crate::vertex::{AttributesDescriptor, Mesh};
+
+pub const POST_VERTEX: &str = "src/post.vert";
+
+// Fullscreen effects, every pass samples the previous output through
+// uniform sampler2D screen_texture
+#[derive(Clone)]
+pub enum PostEffect {
+    GammaCorrection { gamma: f32 },
+    // Exposure tone mapping from HDR down to [0, 1]
+    ToneMapping { exposure: f32 },
+    Fxaa,
+    Grayscale,
+    // Darkens the corners, starting radius away from the center (0.5 reaches the edges)
+    Vignette { radius: f32, strength: f32 },
+    // Fragment shader file, gets the same TexCoord, screen_texture and texel_size
+    Custom { fragment: String },
+}
+
+impl PostEffect {
+    fn fragment_path(&self) -> &str {
+        match self {
+            PostEffect::GammaCorrection { .. } => "src/post_gamma.frag",
+            PostEffect::ToneMapping { .. } => "src/post_tonemap.frag",
+            PostEffect::Fxaa => "src/post_fxaa.frag",
+            PostEffect::Grayscale => "src/post_grayscale.frag",
+            PostEffect::Vignette { .. } => "src/post_vignette.frag",
+            PostEffect::Custom { fragment } => fragment,
+        }
+    }
+
+    // The program has to be in use
+    fn set_uniforms(&self, program: &Program) {
+        match self {
+            PostEffect::GammaCorrection { gamma } => program.set_uniform("gamma", *gamma),
+            PostEffect::ToneMapping { exposure } => program.set_uniform("exposure", *exposure),
+            PostEffect::Vignette { radius, strength } => {
+                program.set_uniform("radius", *radius);
+                program.set_uniform("strength", *strength);
+            }
+            PostEffect::Fxaa | PostEffect::Grayscale | PostEffect::Custom { .. } => {}
+        }
+    }
+}
+
+struct PostProcessPass {
+    name: String,
+    effect: PostEffect,
+    program: Program,
+}
+
+/*
+ * The scene is drawn into an HDR offscreen target between begin_scene() and
+ * finish(), then every pass runs in the order it was added. Passes draw into
+ * two targets in turn, the last one draws straight to the window.
+ */
+pub struct PostProcessChain {
+    scene_target: FramebufferDescriptor,
+    pass_targets: [FramebufferDescriptor; 2],
+    passes: Vec<PostProcessPass>,
+    // One triangle covering the whole screen, clipped to it
+    triangle: Mesh,
+}
+
+impl PostProcessChain {
+    pub fn new(
+        width: gl::types::GLsizei,
+        height: gl::types::GLsizei,
+    ) -> Result<PostProcessChain, String> {
+        let scene_target = FramebufferDescriptor::new(
+            width,
+            height,
+            &[ColorFormat::Rgba16F],
+            DepthAttachment::DepthStencilRenderbuffer,
+        )?;
+        let pass_targets = [
+            FramebufferDescriptor::new(width, height, &[ColorFormat::Rgba16F], DepthAttachment::None)?,
+            FramebufferDescriptor::new(width, height, &[ColorFormat::Rgba16F], DepthAttachment::None)?,
+        ];
+
+        // 2 position and 2 texture components
+        let vertices: Vec<f32> = vec![
+            -1.0, -1.0, 0.0, 0.0,
+            3.0, -1.0, 2.0, 0.0,
+            -1.0, 3.0, 0.0, 2.0,
+        ];
+        let attributes = AttributesDescriptor {
+            component_groups: 2,
+            component_nums: vec![2, 2],
+            component_types: vec![gl::FLOAT, gl::FLOAT],
+            component_offsets: vec![0, 2],
+            component_strides: vec![4, 4],
+        };
+
+        Ok(PostProcessChain {
+            scene_target: scene_target,
+            pass_targets: pass_targets,
+            passes: Vec::new(),
+            triangle: Mesh::new(vertices, vec![0, 1, 2], attributes),
+        })
+    }
+
+    // Appends a pass, names have to be unique so the pass can be removed again
+    pub fn add_effect(&mut self, name: &str, effect: PostEffect) -> Result<(), String> {
+        if self.passes.iter().any(|pass| pass.name == name) {
+            return Err(format!("post effect '{}' already exists", name));
+        }
+
+        let program = Program::from_files(POST_VERTEX, effect.fragment_path())?;
+
+        unsafe {
+            gl::UseProgram(program.id);
+        }
+        program.set_uniform("screen_texture", 0);
+
+        self.passes.push(PostProcessPass {
+            name: name.to_string(),
+            effect: effect,
+            program: program,
+        });
+
+        Ok(())
+    }
+
+    // Returns false when there's no pass with that name
+    pub fn remove_effect(&mut self, name: &str) -> bool {
+        let pass_count = self.passes.len();
+        self.passes.retain(|pass| pass.name != name);
+        self.passes.len() != pass_count
+    }
+
+    pub fn has_effect(&self, name: &str) -> bool {
+        self.passes.iter().any(|pass| pass.name == name)
+    }
+
+    pub fn effect_names(&self) -> Vec<&str> {
+        self.passes.iter().map(|pass| pass.name.as_str()).collect()
+    }
+
+    pub fn resize(
+        &mut self,
+        width: gl::types::GLsizei,
+        height: gl::types::GLsizei,
+    ) -> Result<(), String> {
+        self.scene_target.resize(width, height)?;
+        for target in self.pass_targets.iter_mut() {
+            target.resize(width, height)?;
+        }
+
+        Ok(())
+    }
+
+    // Scene draws after this go to the offscreen target, the caller clears it
+    pub fn begin_scene(&self) {
+        self.scene_target.bind();
+    }
+
+    /*
+     * Runs every pass and leaves the result in output, or the window's
+     * framebuffer (screen_width x screen_height) when output is None.
+     * That framebuffer is left bound with a viewport covering it.
+     */
+    pub fn finish(
+        &self,
+        output: Option<&FramebufferDescriptor>,
+        screen_width: gl::types::GLsizei,
+        screen_height: gl::types::GLsizei,
+    ) {
+        let bind_output = || match output {
+            Some(framebuffer) => framebuffer.bind(),
+            None => {
+                FramebufferDescriptor::bind_default();
+                unsafe {
+                    gl::Viewport(0, 0, screen_width, screen_height);
+                }
+            }
+        };
+
+        if self.passes.is_empty() {
+            match output {
+                Some(framebuffer) => self.scene_target.blit_to_framebuffer(framebuffer, gl::NEAREST),
+                None => self
+                    .scene_target
+                    .blit_to_screen(screen_width, screen_height, gl::NEAREST),
+            }
+            bind_output();
+            return;
+        }
+
+        let texel_size = Vec2::new(
+            1.0 / self.scene_target.width() as f32,
+            1.0 / self.scene_target.height() as f32,
+        );
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+
+        let mut input = &self.scene_target;
+
+        for (pass_idx, pass) in self.passes.iter().enumerate() {
+            let output = &self.pass_targets[pass_idx % 2];
+
+            if pass_idx == self.passes.len() - 1 {
+                bind_output();
+            } else {
+                output.bind();
+            }
+
+            unsafe {
+                gl::UseProgram(pass.program.id);
+            }
+            input.bind_color_texture(0, 0);
+            if pass.program.has_uniform("texel_size") {
+                pass.program.set_uniform("texel_size", texel_size);
+            }
+            pass.effect.set_uniforms(&pass.program);
+
+            self.triangle.bind_vao();
+            self.triangle.render();
+
+            input = output;
+        }
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+}
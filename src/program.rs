@@ -1,13 +1,30 @@
 use crate::shader::*;
+use crate::uniform::{uniform_location, warn_inactive_uniform, UniformDescriptor, UniformPackedParam};
+use crate::vertex::AttributesDescriptor;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::CString;
 
 use crate::gl;
 
+// A uniform or vertex attribute the linked program actually uses,
+// size is the number of elements for arrays and 1 otherwise
+#[derive(Clone, Debug)]
+pub struct ActiveVariable {
+    pub name: String,
+    pub gl_type: gl::types::GLenum,
+    pub size: gl::types::GLint,
+    pub location: gl::types::GLint,
+}
+
 #[derive(Clone)]
 pub struct Program {
     pub id: gl::types::GLuint,
     shader_ids: Vec<gl::types::GLuint>,
+    uniform_locations: RefCell<HashMap<String, gl::types::GLint>>,
+    active_uniforms: Vec<ActiveVariable>,
+    active_attributes: Vec<ActiveVariable>,
 }
 
 impl Program {
@@ -16,6 +33,9 @@ impl Program {
             Program {
                 id: gl::CreateProgram(),
                 shader_ids: Vec::new(),
+                uniform_locations: RefCell::new(HashMap::new()),
+                active_uniforms: Vec::new(),
+                active_attributes: Vec::new(),
             }
         }
     }
@@ -24,7 +44,38 @@ impl Program {
         self.shader_ids.push(shader.id);
     }
 
-    pub fn link_shaders(&self) -> Result<(), String> {
+    // Compiles and links a vertex/fragment shader pair, for the programs
+    // the renderer needs itself rather than the ones a scene file names
+    pub fn from_files(vertex_path: &str, fragment_path: &str) -> Result<Program, String> {
+        let mut shaders = Vec::new();
+
+        for (path, kind) in [(vertex_path, gl::VERTEX_SHADER), (fragment_path, gl::FRAGMENT_SHADER)] {
+            if !std::path::Path::new(path).is_file() {
+                return Err(format!("shader file '{}' not found", path));
+            }
+
+            let mut shader = Shader::new(path, kind);
+            if let Err(e) = shader.compile() {
+                return Err(format!("failed to compile '{}': {}", path, e));
+            }
+            shaders.push(shader);
+        }
+
+        let mut program = Program::new();
+        for shader in shaders.iter() {
+            program.add_shader(shader);
+        }
+
+        match program.link_shaders() {
+            Ok(()) => Ok(program),
+            Err(e) => Err(format!(
+                "failed to link '{}' with '{}': {}",
+                vertex_path, fragment_path, e
+            )),
+        }
+    }
+
+    pub fn link_shaders(&mut self) -> Result<(), String> {
         for shader in self.shader_ids.iter() {
             unsafe {
                 gl::AttachShader(self.id, *shader);
@@ -62,8 +113,168 @@ impl Program {
             }
         }
 
+        self.active_uniforms = self.query_active_variables(
+            gl::ACTIVE_UNIFORMS,
+            gl::ACTIVE_UNIFORM_MAX_LENGTH,
+        );
+        self.active_attributes = self.query_active_variables(
+            gl::ACTIVE_ATTRIBUTES,
+            gl::ACTIVE_ATTRIBUTE_MAX_LENGTH,
+        );
+
+        let mut uniform_locations = self.uniform_locations.borrow_mut();
+        uniform_locations.clear();
+        // Members of uniform blocks have no location of their own
+        for uniform in self.active_uniforms.iter().filter(|uniform| uniform.location != -1) {
+            uniform_locations.insert(uniform.name.clone(), uniform.location);
+        }
+
+        Ok(())
+    }
+
+    fn query_active_variables(
+        &self,
+        count_param: gl::types::GLenum,
+        max_length_param: gl::types::GLenum,
+    ) -> Vec<ActiveVariable> {
+        let mut count: gl::types::GLint = 0;
+        let mut max_length: gl::types::GLint = 0;
+
+        unsafe {
+            gl::GetProgramiv(self.id, count_param, &mut count);
+            gl::GetProgramiv(self.id, max_length_param, &mut max_length);
+        }
+
+        let mut variables = Vec::with_capacity(count as usize);
+
+        for idx in 0..count as gl::types::GLuint {
+            let mut name_buffer: Vec<u8> = vec![0; max_length.max(1) as usize];
+            let mut length: gl::types::GLsizei = 0;
+            let mut size: gl::types::GLint = 0;
+            let mut gl_type: gl::types::GLenum = 0;
+
+            unsafe {
+                if count_param == gl::ACTIVE_UNIFORMS {
+                    gl::GetActiveUniform(
+                        self.id,
+                        idx,
+                        max_length,
+                        &mut length,
+                        &mut size,
+                        &mut gl_type,
+                        name_buffer.as_mut_ptr() as *mut gl::types::GLchar,
+                    );
+                } else {
+                    gl::GetActiveAttrib(
+                        self.id,
+                        idx,
+                        max_length,
+                        &mut length,
+                        &mut size,
+                        &mut gl_type,
+                        name_buffer.as_mut_ptr() as *mut gl::types::GLchar,
+                    );
+                }
+            }
+
+            let name = String::from_utf8_lossy(&name_buffer[..length as usize]).into_owned();
+            let location = if count_param == gl::ACTIVE_UNIFORMS {
+                uniform_location(self.id, &name)
+            } else {
+                unsafe {
+                    gl::GetAttribLocation(self.id, CString::new(name.clone()).unwrap().as_ptr())
+                }
+            };
+
+            variables.push(ActiveVariable {
+                name: name,
+                gl_type: gl_type,
+                size: size,
+                location: location,
+            });
+        }
+
+        variables
+    }
+
+    pub fn active_uniforms(&self) -> &[ActiveVariable] {
+        &self.active_uniforms
+    }
+
+    pub fn active_attributes(&self) -> &[ActiveVariable] {
+        &self.active_attributes
+    }
+
+    // Unlike uniform_location() this doesn't warn about missing names
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.active_uniforms
+            .iter()
+            .any(|uniform| uniform.name == name && uniform.location != -1)
+    }
+
+    // One descriptor per active uniform, samplers are left to TextureDescriptor
+    pub fn uniform_descriptors(&self) -> Vec<UniformDescriptor> {
+        self.active_uniforms
+            .iter()
+            .filter(|uniform| takes_uniform_descriptor(uniform))
+            .map(|uniform| UniformDescriptor::new(self.id, &uniform.name))
+            .collect()
+    }
+
+    // Checks that every vertex shader input has an attribute at its
+    // layout (location = N) and that no attribute carries more components
+    // than the input takes. Fewer are fine, OpenGL fills in the rest.
+    pub fn check_attributes(&self, attributes: &AttributesDescriptor) -> Result<(), String> {
+        for input in self.active_attributes.iter() {
+            // Built-ins like gl_VertexID have no location
+            if input.location < 0 {
+                continue;
+            }
+
+            let location = input.location as usize;
+            if location >= attributes.component_groups as usize {
+                return Err(format!(
+                    "vertex input '{}' at location {} has no matching attribute, only {} are set up",
+                    input.name, location, attributes.component_groups
+                ));
+            }
+
+            if let Some(expected) = component_count(input.gl_type) {
+                let provided = attributes.component_nums[location];
+                if provided > expected {
+                    return Err(format!(
+                        "vertex input '{}' at location {} takes {} components but the attribute has {}",
+                        input.name, location, expected, provided
+                    ));
+                }
+            }
+        }
+
         Ok(())
     }
+
+    // Locations are looked up once per name, unknown names are
+    // remembered as -1 so they only get reported the first time
+    pub fn uniform_location(&self, name: &str) -> gl::types::GLint {
+        if let Some(location) = self.uniform_locations.borrow().get(name) {
+            return *location;
+        }
+
+        let location = uniform_location(self.id, name);
+        if location == -1 {
+            warn_inactive_uniform(self.id, name);
+        }
+        self.uniform_locations
+            .borrow_mut()
+            .insert(name.to_string(), location);
+
+        location
+    }
+
+    // The program has to be in use
+    pub fn set_uniform(&self, name: &str, value: impl Into<UniformPackedParam>) {
+        value.into().upload(self.uniform_location(name));
+    }
 }
 
 impl Drop for Program {
@@ -74,9 +285,124 @@ impl Drop for Program {
     }
 }
 
+// Members of uniform blocks have no location of their own
+fn takes_uniform_descriptor(uniform: &ActiveVariable) -> bool {
+    uniform.location != -1 && !is_sampler(uniform.gl_type)
+}
+
+pub fn is_matrix(gl_type: gl::types::GLenum) -> bool {
+    matches!(
+        gl_type,
+        gl::FLOAT_MAT2
+            | gl::FLOAT_MAT3
+            | gl::FLOAT_MAT4
+            | gl::FLOAT_MAT2x3
+            | gl::FLOAT_MAT2x4
+            | gl::FLOAT_MAT3x2
+            | gl::FLOAT_MAT3x4
+            | gl::FLOAT_MAT4x2
+            | gl::FLOAT_MAT4x3
+    )
+}
+
+pub fn is_sampler(gl_type: gl::types::GLenum) -> bool {
+    matches!(
+        gl_type,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_1D_SHADOW
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_1D_ARRAY
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_1D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_MULTISAMPLE
+            | gl::SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::SAMPLER_CUBE_SHADOW
+            | gl::SAMPLER_BUFFER
+            | gl::SAMPLER_2D_RECT
+            | gl::SAMPLER_2D_RECT_SHADOW
+            | gl::INT_SAMPLER_1D
+            | gl::INT_SAMPLER_2D
+            | gl::INT_SAMPLER_3D
+            | gl::INT_SAMPLER_CUBE
+            | gl::INT_SAMPLER_1D_ARRAY
+            | gl::INT_SAMPLER_2D_ARRAY
+            | gl::INT_SAMPLER_2D_MULTISAMPLE
+            | gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::INT_SAMPLER_BUFFER
+            | gl::INT_SAMPLER_2D_RECT
+            | gl::UNSIGNED_INT_SAMPLER_1D
+            | gl::UNSIGNED_INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_3D
+            | gl::UNSIGNED_INT_SAMPLER_CUBE
+            | gl::UNSIGNED_INT_SAMPLER_1D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_BUFFER
+            | gl::UNSIGNED_INT_SAMPLER_2D_RECT
+    )
+}
+
+// Number of components of scalar and vector GLSL types, None for
+// matrices, samplers and anything else
+pub fn component_count(gl_type: gl::types::GLenum) -> Option<gl::types::GLint> {
+    match gl_type {
+        gl::FLOAT | gl::INT | gl::UNSIGNED_INT | gl::BOOL => Some(1),
+        gl::FLOAT_VEC2 | gl::INT_VEC2 | gl::UNSIGNED_INT_VEC2 | gl::BOOL_VEC2 => Some(2),
+        gl::FLOAT_VEC3 | gl::INT_VEC3 | gl::UNSIGNED_INT_VEC3 | gl::BOOL_VEC3 => Some(3),
+        gl::FLOAT_VEC4 | gl::INT_VEC4 | gl::UNSIGNED_INT_VEC4 | gl::BOOL_VEC4 => Some(4),
+        _ => None,
+    }
+}
+
 pub fn create_whitespace_cstring_with_len(len: usize) -> CString {
     // allocate buffer of correct size
     let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
     buffer.extend([b' '].iter().cycle().take(len));
     unsafe { CString::from_vec_unchecked(buffer) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform(gl_type: gl::types::GLenum, location: gl::types::GLint) -> ActiveVariable {
+        ActiveVariable {
+            name: "value".to_string(),
+            gl_type: gl_type,
+            size: 1,
+            location: location,
+        }
+    }
+
+    #[test]
+    fn type_helpers() {
+        for gl_type in [gl::FLOAT_MAT2, gl::FLOAT_MAT4, gl::FLOAT_MAT2x3, gl::FLOAT_MAT4x3] {
+            assert!(is_matrix(gl_type));
+            assert!(!is_sampler(gl_type));
+            assert_eq!(component_count(gl_type), None);
+        }
+        for gl_type in [gl::SAMPLER_2D, gl::SAMPLER_2D_SHADOW, gl::SAMPLER_CUBE, gl::UNSIGNED_INT_SAMPLER_3D] {
+            assert!(is_sampler(gl_type));
+            assert!(!is_matrix(gl_type));
+        }
+        assert!(!is_matrix(gl::FLOAT_VEC4));
+        assert!(!is_sampler(gl::INT));
+        assert_eq!(component_count(gl::BOOL_VEC3), Some(3));
+    }
+
+    #[test]
+    fn descriptors_for_everything_but_samplers() {
+        assert!(takes_uniform_descriptor(&uniform(gl::FLOAT, 0)));
+        assert!(takes_uniform_descriptor(&uniform(gl::UNSIGNED_INT_VEC2, 1)));
+        assert!(takes_uniform_descriptor(&uniform(gl::FLOAT_MAT4, 2)));
+        assert!(takes_uniform_descriptor(&uniform(gl::FLOAT_MAT3x2, 3)));
+        assert!(!takes_uniform_descriptor(&uniform(gl::SAMPLER_2D, 4)));
+        // In a uniform block
+        assert!(!takes_uniform_descriptor(&uniform(gl::FLOAT_MAT4, -1)));
+    }
+}
@@ -15,37 +15,54 @@ impl Quad {
 
         match center_offset {
             (x, y, z) if x != 0.0 => {
+                // Walls face the origin
+                let normal = [-x.signum(), 0.0, 0.0];
                 vertices_quad.extend_from_slice(&[x, 0.0, -width / 2.0]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomLeft, texture_scale_factor));
                 vertices_quad.extend_from_slice(&[x, height,  -width / 2.0]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopLeft, texture_scale_factor));
                 vertices_quad.extend_from_slice(&[x, 0.0,  width / 2.0]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomRight, texture_scale_factor));
                 vertices_quad.extend_from_slice(&[x,  height,  width / 2.0]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopRight, texture_scale_factor));
 
                 indices_quad.extend_from_slice(&[0,1,2, 1,3,2 ]);
             },
             (x, y, z) if y != 0.0 => {
+                // Floors face up
+                let normal = [0.0, 1.0, 0.0];
                 vertices_quad.extend_from_slice(&[-width / 2.0, y, -width / 2.0]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomLeft, texture_scale_factor));
                 vertices_quad.extend_from_slice(&[ width / 2.0, y, -width / 2.0]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomRight, texture_scale_factor));
                 vertices_quad.extend_from_slice(&[-width / 2.0, y,  width / 2.0]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopLeft, texture_scale_factor));
                 vertices_quad.extend_from_slice(&[ width / 2.0, y,  width / 2.0]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopRight, texture_scale_factor));
 
                 indices_quad.extend_from_slice(&[0,1,2, 1,3,2 ]);
             },
             (x, y, z) if z != 0.0 => {
+                let normal = [0.0, 0.0, -z.signum()];
                 vertices_quad.extend_from_slice(&[-width / 2.0, 0.0, z]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomLeft, texture_scale_factor));
                 vertices_quad.extend_from_slice(&[width / 2.0,  0.0, z]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::BottomRight, texture_scale_factor));
                 vertices_quad.extend_from_slice(&[-width / 2.0, height, z]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopLeft, texture_scale_factor));
                 vertices_quad.extend_from_slice(&[width / 2.0,  height, z]);
+                vertices_quad.extend_from_slice(&normal);
                 vertices_quad.extend_from_slice(&Cube::generate_texture_coords(TextureCorner::TopRight, texture_scale_factor));
 
                 indices_quad.extend_from_slice(&[0,1,2, 1,3,2 ]);
@@ -0,0 +1,983 @@
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+
+use glam::*;
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::application::{FRAGMENT_SHADER, VERTEX_SHADER};
+use crate::cube::Cube;
+use crate::gl;
+use crate::gltf_loader;
+use crate::postprocess::PostEffect;
+use crate::light::{Attenuation, Light, LightKind, Material};
+use crate::program::Program;
+use crate::quad::Quad;
+use crate::scene_graph::{NodeLabel, SceneGraph, Transform};
+use crate::shadow::ShadowSettings;
+use crate::shader::Shader;
+use crate::text::{TextManager, DEFAULT_FONT, DEFAULT_FONT_SIZE};
+use crate::texture::TextureDescriptor;
+use crate::uniform::*;
+use crate::uniform_block::MAX_LIGHTS;
+use crate::vertex::{AttributesDescriptor, Mesh, Model};
+
+/*
+ * Layout of a scene file:
+ *
+ * [[program]]                  named shader programs, nodes refer to them by name
+ * [[node]]                     one scene graph node each, with an optional mesh, its textures,
+ *                              uniforms, material, local transform, the name of its parent node
+ *                              and a label drawn over it
+ * [[light]]                    directional, point and spot lights, at most MAX_LIGHTS
+ * [shadows]                    optional shadow map settings, turns shadows on for the
+ *                              first directional or spot light with cast_shadows = true
+ * [[post_effect]]              fullscreen effects run in order on the rendered scene
+ * [text]                       optional shaders for the TextManager, its font and pixel size,
+ *                              fallback fonts for characters the font doesn't have and
+ *                              sdf = true for distance field glyphs that scale cleanly
+ */
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    #[serde(default, rename = "program")]
+    programs: Vec<ProgramEntry>,
+    #[serde(default, rename = "node")]
+    nodes: Vec<NodeEntry>,
+    #[serde(default, rename = "light")]
+    lights: Vec<Spanned<LightEntry>>,
+    shadows: Option<Spanned<ShadowsEntry>>,
+    #[serde(default, rename = "post_effect")]
+    post_effects: Vec<Spanned<PostEffectEntry>>,
+    text: Option<TextEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProgramEntry {
+    name: Spanned<String>,
+    vertex: Spanned<String>,
+    fragment: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextEntry {
+    vertex: Spanned<String>,
+    fragment: Spanned<String>,
+    font: Option<Spanned<String>>,
+    font_size: Option<Spanned<u32>>,
+    #[serde(default)]
+    fallback_fonts: Vec<Spanned<String>>,
+    #[serde(default)]
+    sdf: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeEntry {
+    name: Option<Spanned<String>>,
+    parent: Option<Spanned<String>>,
+    program: Option<Spanned<String>>,
+    mesh: Option<Spanned<MeshEntry>>,
+    #[serde(default)]
+    textures: Vec<TextureEntry>,
+    #[serde(default)]
+    uniforms: Vec<UniformEntry>,
+    material: Option<Spanned<MaterialEntry>>,
+    #[serde(default)]
+    transform: TransformEntry,
+    label: Option<Spanned<LabelEntry>>,
+}
+
+// label = { text = "cube", offset = [0.0, 0.1, 0.0], height = 0.04, color = [1.0, 1.0, 1.0] }
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LabelEntry {
+    text: String,
+    #[serde(default)]
+    offset: [f32; 3],
+    #[serde(default = "default_label_height")]
+    height: f32,
+    #[serde(default = "default_label_color")]
+    color: [f32; 3],
+}
+
+fn default_label_height() -> f32 {
+    0.04
+}
+
+fn default_label_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MeshEntry {
+    Cube {
+        side_length: f32,
+        #[serde(default)]
+        center: [f32; 3],
+    },
+    Quad {
+        width: f32,
+        height: f32,
+        center_offset: [f32; 3],
+        #[serde(default)]
+        center: [f32; 3],
+        #[serde(default = "default_texture_scale")]
+        texture_scale: [f32; 2],
+    },
+    File {
+        path: String,
+    },
+}
+
+fn default_texture_scale() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+// Anything left out keeps the value of Material::default()
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialEntry {
+    ambient: Option<[f32; 3]>,
+    diffuse: Option<[f32; 3]>,
+    specular: Option<[f32; 3]>,
+    shininess: Option<f32>,
+}
+
+impl MaterialEntry {
+    fn to_material(&self) -> Material {
+        let mut material = Material::default();
+
+        if let Some(ambient) = self.ambient {
+            material.ambient = Vec3::from_array(ambient);
+        }
+        if let Some(diffuse) = self.diffuse {
+            material.diffuse = Vec3::from_array(diffuse);
+        }
+        if let Some(specular) = self.specular {
+            material.specular = Vec3::from_array(specular);
+        }
+        if let Some(shininess) = self.shininess {
+            material.shininess = shininess;
+        }
+
+        material
+    }
+}
+
+// Attenuation is [constant, linear, quadratic], spot angles are in degrees
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LightEntry {
+    Directional {
+        direction: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default)]
+        cast_shadows: bool,
+    },
+    Point {
+        position: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_attenuation")]
+        attenuation: [f32; 3],
+    },
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        inner_angle: f32,
+        outer_angle: f32,
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_attenuation")]
+        attenuation: [f32; 3],
+        #[serde(default)]
+        cast_shadows: bool,
+    },
+}
+
+fn default_light_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_attenuation() -> [f32; 3] {
+    [1.0, 0.0, 0.0]
+}
+
+impl LightEntry {
+    fn to_light(&self) -> Light {
+        let attenuation = |terms: &[f32; 3]| Attenuation::new(terms[0], terms[1], terms[2]);
+
+        match self {
+            LightEntry::Directional {
+                direction,
+                color,
+                cast_shadows,
+            } => Light {
+                cast_shadows: *cast_shadows,
+                ..Light::with_color(
+                    LightKind::Directional {
+                        direction: Vec3::from_array(*direction),
+                    },
+                    Vec3::from_array(*color),
+                )
+            },
+            LightEntry::Point {
+                position,
+                color,
+                attenuation: terms,
+            } => Light::with_color(
+                LightKind::Point {
+                    position: Vec3::from_array(*position),
+                    attenuation: attenuation(terms),
+                },
+                Vec3::from_array(*color),
+            ),
+            LightEntry::Spot {
+                position,
+                direction,
+                inner_angle,
+                outer_angle,
+                color,
+                attenuation: terms,
+                cast_shadows,
+            } => Light {
+                cast_shadows: *cast_shadows,
+                ..Light::with_color(
+                    LightKind::Spot {
+                        position: Vec3::from_array(*position),
+                        direction: Vec3::from_array(*direction),
+                        inner_angle: *inner_angle,
+                        outer_angle: *outer_angle,
+                        attenuation: attenuation(terms),
+                    },
+                    Vec3::from_array(*color),
+                )
+            },
+        }
+    }
+}
+
+// The effect is named after its type (or fragment file for custom ones),
+// so each can only be in the chain once
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum PostEffectEntry {
+    GammaCorrection {
+        #[serde(default = "default_gamma")]
+        gamma: f32,
+    },
+    ToneMapping {
+        #[serde(default = "default_exposure")]
+        exposure: f32,
+    },
+    Fxaa,
+    Grayscale,
+    Vignette {
+        #[serde(default = "default_vignette_radius")]
+        radius: f32,
+        #[serde(default = "default_vignette_strength")]
+        strength: f32,
+    },
+    Custom {
+        fragment: String,
+    },
+}
+
+fn default_gamma() -> f32 {
+    2.2
+}
+
+fn default_exposure() -> f32 {
+    1.0
+}
+
+fn default_vignette_radius() -> f32 {
+    0.5
+}
+
+fn default_vignette_strength() -> f32 {
+    0.5
+}
+
+impl PostEffectEntry {
+    fn to_effect(&self) -> (String, PostEffect) {
+        match self {
+            PostEffectEntry::GammaCorrection { gamma } => (
+                "gamma_correction".to_string(),
+                PostEffect::GammaCorrection { gamma: *gamma },
+            ),
+            PostEffectEntry::ToneMapping { exposure } => (
+                "tone_mapping".to_string(),
+                PostEffect::ToneMapping {
+                    exposure: *exposure,
+                },
+            ),
+            PostEffectEntry::Fxaa => ("fxaa".to_string(), PostEffect::Fxaa),
+            PostEffectEntry::Grayscale => ("grayscale".to_string(), PostEffect::Grayscale),
+            PostEffectEntry::Vignette { radius, strength } => (
+                "vignette".to_string(),
+                PostEffect::Vignette {
+                    radius: *radius,
+                    strength: *strength,
+                },
+            ),
+            PostEffectEntry::Custom { fragment } => (
+                fragment.clone(),
+                PostEffect::Custom {
+                    fragment: fragment.clone(),
+                },
+            ),
+        }
+    }
+}
+
+// Anything left out keeps the value of ShadowSettings::default()
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShadowsEntry {
+    resolution: Option<i32>,
+    min_bias: Option<f32>,
+    max_bias: Option<f32>,
+    pcf_radius: Option<i32>,
+    directional_extent: Option<f32>,
+}
+
+impl ShadowsEntry {
+    fn to_settings(&self) -> ShadowSettings {
+        let mut settings = ShadowSettings::default();
+
+        if let Some(resolution) = self.resolution {
+            settings.resolution = resolution;
+        }
+        if let Some(min_bias) = self.min_bias {
+            settings.min_bias = min_bias;
+        }
+        if let Some(max_bias) = self.max_bias {
+            settings.max_bias = max_bias;
+        }
+        if let Some(pcf_radius) = self.pcf_radius {
+            settings.pcf_radius = pcf_radius;
+        }
+        if let Some(directional_extent) = self.directional_extent {
+            settings.directional_extent = directional_extent;
+        }
+
+        settings
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureEntry {
+    uniform: Spanned<String>,
+    file: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UniformEntry {
+    name: Spanned<String>,
+    value: Option<UniformValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UniformValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl UniformValue {
+    fn to_packed_param(&self) -> UniformPackedParam {
+        match self {
+            UniformValue::Bool(value) => (*value).into(),
+            UniformValue::Int(value) => (*value).into(),
+            UniformValue::Float(value) => (*value).into(),
+            UniformValue::Vec2(value) => Vec2::from_array(*value).into(),
+            UniformValue::Vec3(value) => Vec3::from_array(*value).into(),
+            UniformValue::Vec4(value) => Vec4::from_array(*value).into(),
+        }
+    }
+}
+
+// Rotation is given as XYZ euler angles in degrees
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct TransformEntry {
+    translation: [f32; 3],
+    rotation: [f32; 3],
+    scale: [f32; 3],
+}
+
+impl Default for TransformEntry {
+    fn default() -> TransformEntry {
+        TransformEntry {
+            translation: [0.0, 0.0, 0.0],
+            rotation: [0.0, 0.0, 0.0],
+            scale: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl TransformEntry {
+    fn to_transform(&self) -> Transform {
+        Transform::new(
+            Vec3::from(self.translation),
+            Quat::from_euler(
+                EulerRot::XYZ,
+                self.rotation[0].to_radians(),
+                self.rotation[1].to_radians(),
+                self.rotation[2].to_radians(),
+            ),
+            Vec3::from(self.scale),
+        )
+    }
+}
+
+// Everything a scene file describes, ready to be handed to the Application
+pub struct SceneDescription {
+    pub scene: SceneGraph,
+    pub lights: Vec<Light>,
+    pub shadows: Option<ShadowSettings>,
+    // Name and effect, in chain order
+    pub post_effects: Vec<(String, PostEffect)>,
+    pub text_manager: Option<TextManager>,
+}
+
+struct SceneSource<'a> {
+    path: &'a str,
+    text: String,
+}
+
+impl<'a> SceneSource<'a> {
+    fn error_at(&self, span: Range<usize>, message: String) -> String {
+        let line = self.text[..span.start.min(self.text.len())].matches('\n').count() + 1;
+        format!("{}:{}: {}", self.path, line, message)
+    }
+
+    fn compile_shader(
+        &self,
+        path: &Spanned<String>,
+        kind: gl::types::GLenum,
+    ) -> Result<Shader, String> {
+        if !Path::new(path.get_ref()).is_file() {
+            return Err(self.error_at(
+                path.span(),
+                format!("shader file '{}' not found", path.get_ref()),
+            ));
+        }
+
+        let mut shader = Shader::new(path.get_ref(), kind);
+        match shader.compile() {
+            Ok(()) => Ok(shader),
+            Err(e) => Err(self.error_at(
+                path.span(),
+                format!("failed to compile '{}': {}", path.get_ref(), e),
+            )),
+        }
+    }
+
+    fn build_program(
+        &self,
+        vertex: &Spanned<String>,
+        fragment: &Spanned<String>,
+    ) -> Result<Program, String> {
+        let vert_shader = self.compile_shader(vertex, VERTEX_SHADER)?;
+        let frag_shader = self.compile_shader(fragment, FRAGMENT_SHADER)?;
+
+        let mut program = Program::new();
+        program.add_shader(&vert_shader);
+        program.add_shader(&frag_shader);
+
+        match program.link_shaders() {
+            Ok(()) => Ok(program),
+            Err(e) => Err(self.error_at(
+                vertex.span(),
+                format!(
+                    "failed to link '{}' with '{}': {}",
+                    vertex.get_ref(),
+                    fragment.get_ref(),
+                    e
+                ),
+            )),
+        }
+    }
+
+    fn build_meshes(
+        &self,
+        mesh: &Spanned<MeshEntry>,
+        program_id: gl::types::GLuint,
+    ) -> Result<Model, String> {
+        // 3 position, 3 normal and 2 texture components, same as the model loaders
+        let primitive_attr = || AttributesDescriptor {
+            component_groups: 3,
+            component_nums: vec![3, 3, 2],
+            component_types: vec![gl::FLOAT, gl::FLOAT, gl::FLOAT],
+            component_offsets: vec![0, 3, 6],
+            component_strides: vec![8, 8, 8],
+        };
+
+        match mesh.get_ref() {
+            MeshEntry::Cube {
+                side_length,
+                center,
+            } => {
+                let cube = Cube::new(*side_length, (center[0], center[1], center[2]));
+                let mut model = Model::new();
+                model.add_mesh(Mesh::new(cube.vertices, cube.indices, primitive_attr()));
+                Ok(model)
+            }
+            MeshEntry::Quad {
+                width,
+                height,
+                center_offset,
+                center,
+                texture_scale,
+            } => {
+                // Quad only knows how to build planes facing one of the axes
+                if center_offset.iter().filter(|component| **component != 0.0).count() != 1 {
+                    return Err(self.error_at(
+                        mesh.span(),
+                        "quad center_offset needs exactly one non-zero component".to_string(),
+                    ));
+                }
+
+                let quad = Quad::new(
+                    *width,
+                    *height,
+                    (center_offset[0], center_offset[1], center_offset[2]),
+                    (center[0], center[1], center[2]),
+                    (texture_scale[0], texture_scale[1]),
+                );
+                let mut model = Model::new();
+                model.add_mesh(Mesh::new(quad.vertices, quad.indices, primitive_attr()));
+                Ok(model)
+            }
+            MeshEntry::File { path } => {
+                if !Path::new(path).is_file() {
+                    return Err(self.error_at(
+                        mesh.span(),
+                        format!("model file '{}' not found", path),
+                    ));
+                }
+
+                let extension = Path::new(path)
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .unwrap_or("")
+                    .to_lowercase();
+
+                let loaded = if extension == "gltf" || extension == "glb" {
+                    gltf_loader::load_gltf(path, program_id)
+                } else {
+                    let mut model = Model::new();
+                    model.parse_mesh_from_file(path, program_id).map(|()| model)
+                };
+
+                match loaded {
+                    Ok(model) => Ok(model),
+                    Err(e) => Err(self.error_at(mesh.span(), e)),
+                }
+            }
+        }
+    }
+
+    fn build_model(
+        &self,
+        programs: &HashMap<String, Rc<Program>>,
+        program_name: &Spanned<String>,
+        mesh: &Spanned<MeshEntry>,
+        textures: &Vec<TextureEntry>,
+        uniforms: &Vec<UniformEntry>,
+        material: &Option<Spanned<MaterialEntry>>,
+    ) -> Result<Model, String> {
+        let program = match programs.get(program_name.get_ref()) {
+            Some(program) => Rc::clone(program),
+            None => {
+                return Err(self.error_at(
+                    program_name.span(),
+                    format!("unknown program '{}'", program_name.get_ref()),
+                ));
+            }
+        };
+
+        let mut model = self.build_meshes(mesh, program.id)?;
+
+        for built_mesh in model.meshes.iter() {
+            if let Err(e) = program.check_attributes(&built_mesh.attributes) {
+                return Err(self.error_at(
+                    mesh.span(),
+                    format!("mesh doesn't fit program '{}': {}", program_name.get_ref(), e),
+                ));
+            }
+        }
+
+        unsafe {
+            gl::UseProgram(program.id);
+        }
+
+        for texture in textures.iter() {
+            self.check_uniform(&program, program_name.get_ref(), &texture.uniform)?;
+
+            if !Path::new(texture.file.get_ref()).is_file() {
+                return Err(self.error_at(
+                    texture.file.span(),
+                    format!("texture file '{}' not found", texture.file.get_ref()),
+                ));
+            }
+
+            let texture_desc = TextureDescriptor::new_detect_format(
+                program.id,
+                texture.uniform.get_ref(),
+                texture.file.get_ref(),
+            )
+            .map_err(|e| self.error_at(texture.file.span(), e))?;
+            for mesh in model.meshes.iter_mut() {
+                mesh.add_texture(texture_desc.clone());
+            }
+        }
+
+        for uniform in uniforms.iter() {
+            self.check_uniform(&program, program_name.get_ref(), &uniform.name)?;
+
+            for mesh in model.meshes.iter_mut() {
+                let mut uniform_desc = UniformDescriptor::new(program.id, uniform.name.get_ref());
+                if let Some(value) = &uniform.value {
+                    uniform_desc.update(value.to_packed_param());
+                }
+                mesh.add_uniform(uniform_desc);
+            }
+        }
+
+        if let Some(material) = material {
+            for mesh in model.meshes.iter_mut() {
+                mesh.material = material.get_ref().to_material();
+            }
+        }
+
+        model.attach_program(program);
+
+        Ok(model)
+    }
+
+    fn check_uniform(
+        &self,
+        program: &Program,
+        program_name: &str,
+        name: &Spanned<String>,
+    ) -> Result<(), String> {
+        if uniform_location(program.id, name.get_ref()) != -1 {
+            Ok(())
+        } else {
+            Err(self.error_at(
+                name.span(),
+                format!(
+                    "unknown uniform '{}' in program '{}'",
+                    name.get_ref(),
+                    program_name
+                ),
+            ))
+        }
+    }
+}
+
+pub fn load_scene(path: &str) -> Result<SceneDescription, String> {
+    let text = match read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            return Err(format!("Failed to read scene file {}: {}", path, e));
+        }
+    };
+
+    let source = SceneSource { path: path, text: text };
+
+    let scene_file: SceneFile = match toml::from_str(&source.text) {
+        Ok(scene_file) => scene_file,
+        Err(e) => {
+            return Err(source.error_at(e.span().unwrap_or(0..0), e.message().to_string()));
+        }
+    };
+
+    let mut programs: HashMap<String, Rc<Program>> = HashMap::new();
+
+    for entry in scene_file.programs.iter() {
+        if programs.contains_key(entry.name.get_ref()) {
+            return Err(source.error_at(
+                entry.name.span(),
+                format!("program '{}' is declared twice", entry.name.get_ref()),
+            ));
+        }
+
+        let program = source.build_program(&entry.vertex, &entry.fragment)?;
+        programs.insert(entry.name.get_ref().clone(), Rc::new(program));
+    }
+
+    let mut scene = SceneGraph::new();
+
+    for entry in scene_file.nodes.iter() {
+        let name = match &entry.name {
+            Some(name) => {
+                if scene.find(name.get_ref()).is_some() {
+                    return Err(source.error_at(
+                        name.span(),
+                        format!("node '{}' is declared twice", name.get_ref()),
+                    ));
+                }
+                name.get_ref().as_str()
+            }
+            None => "",
+        };
+
+        // Parents have to be declared before their children
+        let parent = match &entry.parent {
+            Some(parent_name) => match scene.find(parent_name.get_ref()) {
+                Some(parent_id) => Some(parent_id),
+                None => {
+                    return Err(source.error_at(
+                        parent_name.span(),
+                        format!("unknown parent node '{}'", parent_name.get_ref()),
+                    ));
+                }
+            },
+            None => None,
+        };
+
+        let model = match (&entry.program, &entry.mesh) {
+            (Some(program_name), Some(mesh)) => Some(source.build_model(
+                &programs,
+                program_name,
+                mesh,
+                &entry.textures,
+                &entry.uniforms,
+                &entry.material,
+            )?),
+            (None, Some(mesh)) => {
+                return Err(source.error_at(
+                    mesh.span(),
+                    "a node with a mesh needs a program".to_string(),
+                ));
+            }
+            (_, None) => {
+                let stray_span = entry
+                    .textures
+                    .first()
+                    .map(|texture| texture.uniform.span())
+                    .or(entry.uniforms.first().map(|uniform| uniform.name.span()))
+                    .or(entry.material.as_ref().map(|material| material.span()));
+                if let Some(span) = stray_span {
+                    return Err(source.error_at(
+                        span,
+                        "textures, uniforms and materials need a mesh to be attached to".to_string(),
+                    ));
+                }
+                None
+            }
+        };
+
+        let id = scene.add_node(name, entry.transform.to_transform(), model, parent);
+
+        if let Some(label) = &entry.label {
+            if label.get_ref().height <= 0.0 {
+                return Err(source.error_at(
+                    label.span(),
+                    "label height has to be above 0".to_string(),
+                ));
+            }
+
+            scene.node_mut(id).label = Some(NodeLabel {
+                text: label.get_ref().text.clone(),
+                offset: Vec3::from(label.get_ref().offset),
+                height: label.get_ref().height,
+                color: Vec3::from(label.get_ref().color),
+            });
+        }
+    }
+
+    if scene_file.lights.len() > MAX_LIGHTS {
+        return Err(source.error_at(
+            scene_file.lights[MAX_LIGHTS].span(),
+            format!("a scene can't have more than {} lights", MAX_LIGHTS),
+        ));
+    }
+
+    let lights = scene_file
+        .lights
+        .iter()
+        .map(|light| light.get_ref().to_light())
+        .collect();
+
+    let shadows = match &scene_file.shadows {
+        Some(shadows) => {
+            let settings = shadows.get_ref().to_settings();
+            if settings.resolution <= 0 || settings.pcf_radius < 0 {
+                return Err(source.error_at(
+                    shadows.span(),
+                    "shadow resolution has to be positive and pcf_radius at least 0".to_string(),
+                ));
+            }
+            Some(settings)
+        }
+        None => None,
+    };
+
+    let mut post_effects: Vec<(String, PostEffect)> = Vec::new();
+
+    for entry in scene_file.post_effects.iter() {
+        let (name, effect) = entry.get_ref().to_effect();
+
+        if let PostEffect::Custom { fragment } = &effect {
+            if !Path::new(fragment).is_file() {
+                return Err(source.error_at(
+                    entry.span(),
+                    format!("shader file '{}' not found", fragment),
+                ));
+            }
+        }
+
+        if post_effects.iter().any(|(existing, _)| *existing == name) {
+            return Err(source.error_at(
+                entry.span(),
+                format!("post effect '{}' is declared twice", name),
+            ));
+        }
+
+        post_effects.push((name, effect));
+    }
+
+    let text_manager = match &scene_file.text {
+        Some(text) => {
+            let program = source.build_program(&text.vertex, &text.fragment)?;
+            let mut text_manager = TextManager::new(program)?;
+
+            let size = text.font_size.as_ref().map_or(DEFAULT_FONT_SIZE, |size| *size.get_ref());
+            // The default font isn't in the file, errors about it have no line
+            let font = match &text.font {
+                Some(font) => (font.get_ref().as_str(), Some(font.span())),
+                None => (DEFAULT_FONT, None),
+            };
+            let fallback_fonts = text.fallback_fonts.iter().map(|font| (font.get_ref().as_str(), Some(font.span())));
+
+            // The fallbacks are loaded after the font so it stays the default
+            for (path, span) in std::iter::once(font).chain(fallback_fonts) {
+                let error = |message: String| match span.clone() {
+                    Some(span) => source.error_at(span, message),
+                    None => format!("{}: {}", source.path, message),
+                };
+
+                if !Path::new(path).is_file() {
+                    return Err(error(format!("font file '{}' not found", path)));
+                }
+
+                let loaded = if text.sdf {
+                    text_manager.load_sdf_font(path, size)
+                } else {
+                    text_manager.load_font(path, size)
+                };
+                if let Err(e) = loaded {
+                    return Err(error(e));
+                }
+            }
+
+            Some(text_manager)
+        }
+        None => None,
+    };
+
+    Ok(SceneDescription {
+        scene: scene,
+        lights: lights,
+        shadows: shadows,
+        post_effects: post_effects,
+        text_manager: text_manager,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Loads text as if it was a scene file, these all fail before anything needs GL
+    fn load_error(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!("scene_{}_{}.toml", name, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let error = load_scene(path.to_str().unwrap()).err();
+        let _ = std::fs::remove_file(&path);
+
+        let error = error.expect(text);
+        // Without the temporary path
+        error[path.to_str().unwrap().len()..].to_string()
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let error = load_error("top_key", "[[light]]\ntype = \"directional\"\ndirection = [0.0, -1.0, 0.0]\n\n[camera]\nfov = 45.0\n");
+        assert!(error.starts_with(":5: unknown field `camera`"), "{}", error);
+
+        let error = load_error("node_key", "[[node]]\nname = \"a\"\nscale = 2.0\n");
+        assert!(error.starts_with(":3: unknown field `scale`"), "{}", error);
+
+        let error = load_error("label_key", "[[node]]\nname = \"a\"\nlabel = { text = \"a\", size = 1.0 }\n");
+        assert!(error.starts_with(":3: unknown field `size`"), "{}", error);
+    }
+
+    #[test]
+    fn bad_types() {
+        let error = load_error("name_type", "[[node]]\n\nname = 3\n");
+        assert!(error.starts_with(":3: invalid type: integer `3`, expected a string"), "{}", error);
+
+        let error = load_error("vector_type", "[[node]]\nname = \"a\"\ntransform = { translation = [1.0, 2.0] }\n");
+        assert!(error.starts_with(":3: invalid length 2"), "{}", error);
+
+        let error = load_error("light_type", "[[light]]\ntype = \"laser\"\n");
+        assert!(error.starts_with(":2: unknown variant `laser`"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_references() {
+        let error = load_error(
+            "program",
+            "[[node]]\nname = \"a\"\n\n[[node]]\nname = \"b\"\nprogram = \"lit\"\nmesh = { type = \"cube\", side_length = 1.0 }\n",
+        );
+        assert_eq!(error, ":6: unknown program 'lit'");
+
+        let error = load_error("parent", "[[node]]\nname = \"a\"\n\n[[node]]\nname = \"b\"\nparent = \"c\"\n");
+        assert_eq!(error, ":6: unknown parent node 'c'");
+
+        // Parents come before their children
+        let error = load_error("parent_order", "[[node]]\nname = \"b\"\nparent = \"a\"\n\n[[node]]\nname = \"a\"\n");
+        assert_eq!(error, ":3: unknown parent node 'a'");
+    }
+
+    #[test]
+    fn other_mistakes() {
+        let error = load_error("twice", "[[node]]\nname = \"a\"\n\n[[node]]\nname = \"a\"\n");
+        assert_eq!(error, ":5: node 'a' is declared twice");
+
+        let error = load_error("no_program", "[[node]]\nmesh = { type = \"cube\", side_length = 1.0 }\n");
+        assert_eq!(error, ":2: a node with a mesh needs a program");
+
+        let error = load_error("stray", "[[node]]\nname = \"a\"\nuniforms = [{ name = \"mixvalue\", value = 0.5 }]\n");
+        assert_eq!(error, ":3: textures, uniforms and materials need a mesh to be attached to");
+
+        let error = load_error("label", "[[node]]\nname = \"a\"\nlabel = { text = \"a\", height = 0.0 }\n");
+        assert_eq!(error, ":3: label height has to be above 0");
+    }
+
+    #[test]
+    fn missing_file() {
+        let error = load_scene("res/missing.toml").err().unwrap();
+        assert!(error.starts_with("Failed to read scene file res/missing.toml"), "{}", error);
+    }
+}
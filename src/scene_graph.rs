@@ -0,0 +1,180 @@
+use glam::*;
+
+use crate::vertex::Model;
+
+// Translation, rotation and scale of a node relative to its parent
+#[derive(Clone, Copy)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    pub fn new(translation: Vec3, rotation: Quat, scale: Vec3) -> Transform {
+        Transform {
+            translation: translation,
+            rotation: rotation,
+            scale: scale,
+        }
+    }
+
+    pub fn identity() -> Transform {
+        Transform::new(Vec3::ZERO, Quat::IDENTITY, Vec3::ONE)
+    }
+
+    pub fn to_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+pub type NodeId = usize;
+
+// Text drawn in the scene over a node, facing the camera
+#[derive(Clone, Debug)]
+pub struct NodeLabel {
+    pub text: String,
+    // From the node's origin in world space, so it doesn't turn with the node
+    pub offset: Vec3,
+    // World units per line
+    pub height: f32,
+    pub color: Vec3,
+}
+
+pub struct SceneNode {
+    pub name: String,
+    pub transform: Transform,
+    pub model: Option<Model>,
+    pub label: Option<NodeLabel>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world_matrix: Mat4,
+}
+
+impl SceneNode {
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    // Only up to date after SceneGraph::update_world_matrices()
+    pub fn world_matrix(&self) -> Mat4 {
+        self.world_matrix
+    }
+}
+
+// Nodes live in a single Vec and refer to each other by index,
+// nodes without a parent are the roots of the graph
+pub struct SceneGraph {
+    nodes: Vec<SceneNode>,
+    roots: Vec<NodeId>,
+}
+
+impl SceneGraph {
+    pub fn new() -> SceneGraph {
+        SceneGraph {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    pub fn add_node(
+        &mut self,
+        name: &str,
+        transform: Transform,
+        model: Option<Model>,
+        parent: Option<NodeId>,
+    ) -> NodeId {
+        let id = self.nodes.len();
+
+        self.nodes.push(SceneNode {
+            name: name.to_string(),
+            transform: transform,
+            model: model,
+            label: None,
+            parent: parent,
+            children: Vec::new(),
+            world_matrix: transform.to_matrix(),
+        });
+
+        match parent {
+            Some(parent_id) => self.nodes[parent_id].children.push(id),
+            None => self.roots.push(id),
+        }
+
+        id
+    }
+
+    // Moves a node (and everything below it) under another parent,
+    // or to the roots when parent is None
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), String> {
+        let mut ancestor = parent;
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == id {
+                return Err(format!(
+                    "Node '{}' can't be nested inside itself",
+                    self.nodes[id].name
+                ));
+            }
+            ancestor = self.nodes[ancestor_id].parent;
+        }
+
+        match self.nodes[id].parent {
+            Some(old_parent) => self.nodes[old_parent].children.retain(|child| *child != id),
+            None => self.roots.retain(|root| *root != id),
+        }
+
+        match parent {
+            Some(parent_id) => self.nodes[parent_id].children.push(id),
+            None => self.roots.push(id),
+        }
+        self.nodes[id].parent = parent;
+
+        Ok(())
+    }
+
+    pub fn node(&self, id: NodeId) -> &SceneNode {
+        &self.nodes[id]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut SceneNode {
+        &mut self.nodes[id]
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, SceneNode> {
+        self.nodes.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, SceneNode> {
+        self.nodes.iter_mut()
+    }
+
+    // Walks the graph from the roots down so every parent is done before its children
+    pub fn update_world_matrices(&mut self) {
+        let mut pending: Vec<(NodeId, Mat4)> = self
+            .roots
+            .iter()
+            .map(|root| (*root, Mat4::IDENTITY))
+            .collect();
+
+        while let Some((id, parent_matrix)) = pending.pop() {
+            let world_matrix = parent_matrix * self.nodes[id].transform.to_matrix();
+            self.nodes[id].world_matrix = world_matrix;
+
+            for child in self.nodes[id].children.iter() {
+                pending.push((*child, world_matrix));
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+const INFINITY: f32 = 1e20;
+
+/*
+ * Turns an 8 bit coverage bitmap (like FreeType renders glyphs) into a signed
+ * distance field spread pixels wider on every side, so a width x height bitmap
+ * gives (width + 2 * spread) x (height + 2 * spread) values. 128 is on the edge
+ * of the shape, higher inside and lower outside, falling to 0 at spread pixels
+ * out. Partly covered pixels put the edge inside them by how much is covered,
+ * this is the approach of Mapbox's TinySDF, with the exact Euclidean distance
+ * transform from "Distance Transforms of Sampled Functions" (Felzenszwalb and
+ * Huttenlocher).
+ */
+pub fn distance_field(coverage: &[u8], width: usize, height: usize, spread: usize) -> Vec<u8> {
+    let field_width = width + 2 * spread;
+    let field_height = height + 2 * spread;
+    let size = field_width * field_height;
+
+    // Squared distances to the nearest pixel inside and outside the shape
+    let mut outside = vec![INFINITY; size];
+    let mut inside = vec![0.0; size];
+
+    for y in 0..height {
+        for x in 0..width {
+            let alpha = coverage[y * width + x] as f32 / 255.0;
+            if alpha == 0.0 {
+                continue;
+            }
+
+            let i = (y + spread) * field_width + x + spread;
+            if alpha == 1.0 {
+                outside[i] = 0.0;
+                inside[i] = INFINITY;
+            } else {
+                let d = 0.5 - alpha;
+                outside[i] = if d > 0.0 { d * d } else { 0.0 };
+                inside[i] = if d < 0.0 { d * d } else { 0.0 };
+            }
+        }
+    }
+
+    distance_transform_2d(&mut outside, field_width, field_height);
+    distance_transform_2d(&mut inside, field_width, field_height);
+
+    outside
+        .iter()
+        .zip(inside.iter())
+        .map(|(outside, inside)| {
+            let distance = outside.sqrt() - inside.sqrt();
+            let value = 0.5 - distance / (2.0 * spread.max(1) as f32);
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+// Columns first, then rows
+fn distance_transform_2d(grid: &mut [f32], width: usize, height: usize) {
+    let longest = width.max(height);
+    let mut f = vec![0.0; longest];
+    let mut d = vec![0.0; longest];
+    let mut v = vec![0usize; longest];
+    let mut z = vec![0.0; longest + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            f[y] = grid[y * width + x];
+        }
+        distance_transform_1d(&f[..height], &mut d, &mut v, &mut z);
+        for y in 0..height {
+            grid[y * width + x] = d[y];
+        }
+    }
+
+    for y in 0..height {
+        f[..width].copy_from_slice(&grid[y * width..(y + 1) * width]);
+        distance_transform_1d(&f[..width], &mut d, &mut v, &mut z);
+        grid[y * width..(y + 1) * width].copy_from_slice(&d[..width]);
+    }
+}
+
+// Lower envelope of the parabolas rooted at every f[q], d gets the squared distances
+fn distance_transform_1d(f: &[f32], d: &mut [f32], v: &mut [usize], z: &mut [f32]) {
+    let n = f.len();
+    if n == 0 {
+        return;
+    }
+
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = -INFINITY;
+    z[1] = INFINITY;
+
+    for q in 1..n {
+        let mut s;
+        loop {
+            let r = v[k];
+            s = (f[q] - f[r] + (q * q) as f32 - (r * r) as f32) / (2.0 * (q as f32 - r as f32));
+            if s <= z[k] && k > 0 {
+                k -= 1;
+            } else {
+                break;
+            }
+        }
+
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = INFINITY;
+    }
+
+    k = 0;
+    for q in 0..n {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+        let r = v[k];
+        d[q] = (q as f32 - r as f32) * (q as f32 - r as f32) + f[r];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A width x height bitmap with a filled size x size square at (left, top)
+    fn square(width: usize, height: usize, left: usize, top: usize, size: usize) -> Vec<u8> {
+        let mut coverage = vec![0; width * height];
+        for y in top..top + size {
+            for x in left..left + size {
+                coverage[y * width + x] = 255;
+            }
+        }
+        coverage
+    }
+
+    #[test]
+    fn field_is_spread_wider() {
+        let coverage = square(5, 3, 1, 1, 1);
+        assert_eq!(distance_field(&coverage, 5, 3, 4).len(), (5 + 8) * (3 + 8));
+        assert_eq!(distance_field(&coverage, 5, 3, 0).len(), 5 * 3);
+        assert_eq!(distance_field(&[], 0, 0, 2).len(), 16);
+    }
+
+    #[test]
+    fn inside_is_high_and_far_outside_zero() {
+        let spread = 3;
+        let coverage = square(4, 4, 1, 1, 2);
+        let field = distance_field(&coverage, 4, 4, spread);
+        let field_width = 4 + 2 * spread;
+
+        for y in 1..3 {
+            for x in 1..3 {
+                assert!(field[(y + spread) * field_width + x + spread] > 128);
+            }
+        }
+        // The corners are more than spread away
+        assert_eq!(field[0], 0);
+        assert_eq!(field[field.len() - 1], 0);
+        assert!(field.iter().all(|value| *value < 255));
+    }
+
+    #[test]
+    fn falls_off_with_distance() {
+        let (size, spread) = (9, 6);
+        let coverage = square(size, size, 3, 3, 3);
+        let field = distance_field(&coverage, size, size, spread);
+        let field_width = size + 2 * spread;
+
+        // From pixel centers to the square's edge, in field coordinates
+        let low = (3 + spread) as f32;
+        let high = low + 3.0;
+        let distance = |i: usize| {
+            let x = (i % field_width) as f32 + 0.5;
+            let y = (i / field_width) as f32 + 0.5;
+            let dx = (low - x).max(x - high).max(0.0);
+            let dy = (low - y).max(y - high).max(0.0);
+            (dx * dx + dy * dy).sqrt()
+        };
+
+        let mut by_distance: Vec<(f32, u8)> = (0..field.len()).map(|i| (distance(i), field[i])).collect();
+        by_distance.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        // Every pixel is at most as high as any nearer one
+        let mut nearer_lowest = 255;
+        let mut group_lowest = 255;
+        let mut group_distance = 0.0;
+        for (distance, value) in by_distance.into_iter() {
+            if distance > group_distance {
+                nearer_lowest = nearer_lowest.min(group_lowest);
+                group_lowest = 255;
+                group_distance = distance;
+            }
+            assert!(value <= nearer_lowest, "{} at distance {} is higher than nearer pixels", value, distance);
+            group_lowest = group_lowest.min(value);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use glam::*;
+
+use crate::framebuffer::FramebufferDescriptor;
+use crate::gl;
+use crate::light::{Light, LightKind};
+use crate::program::Program;
+use crate::scene_graph::SceneGraph;
+
+pub const SHADOW_DEPTH_VERTEX: &str = "src/shadow_depth.vert";
+pub const SHADOW_DEPTH_FRAGMENT: &str = "src/shadow_depth.frag";
+
+// Meshes bind their textures from unit 0 up, the shadow map
+// sits on the last unit OpenGL 3.3 guarantees
+pub const SHADOW_MAP_TEXTURE_UNIT: u32 = 15;
+
+#[derive(Clone, Copy)]
+pub struct ShadowSettings {
+    // Width and height of the depth texture
+    pub resolution: i32,
+    // Depth bias against shadow acne, surfaces facing the light get min_bias,
+    // growing up to max_bias for surfaces at a grazing angle
+    pub min_bias: f32,
+    pub max_bias: f32,
+    // PCF averages (2 * pcf_radius + 1)^2 texels around each lookup
+    pub pcf_radius: i32,
+    // Half the width of the square around the origin a directional light covers
+    pub directional_extent: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> ShadowSettings {
+        ShadowSettings {
+            resolution: 2048,
+            min_bias: 0.0005,
+            max_bias: 0.005,
+            pcf_radius: 1,
+            directional_extent: 5.0,
+        }
+    }
+}
+
+// Projection * view of the light, None for point lights
+pub fn light_space_matrix(light: &Light, settings: &ShadowSettings) -> Option<Mat4> {
+    // look_at can't use an up vector parallel to the view direction
+    let up_vector = |direction: Vec3| {
+        if direction.normalize().y.abs() > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        }
+    };
+
+    match light.kind {
+        LightKind::Directional { direction } => {
+            let extent = settings.directional_extent;
+            let eye = -direction.normalize() * extent * 2.0;
+            let view = Mat4::look_at_rh(eye, Vec3::ZERO, up_vector(direction));
+            let projection =
+                Mat4::orthographic_rh_gl(-extent, extent, -extent, extent, 0.01, extent * 4.0);
+            Some(projection * view)
+        }
+        LightKind::Spot {
+            position,
+            direction,
+            outer_angle,
+            ..
+        } => {
+            let view = Mat4::look_at_rh(position, position + direction, up_vector(direction));
+            let fov = (outer_angle * 2.0).min(170.0).to_radians();
+            let projection = Mat4::perspective_rh_gl(fov, 1.0, 0.05, 50.0);
+            Some(projection * view)
+        }
+        LightKind::Point { .. } => None,
+    }
+}
+
+/*
+ * Depth of the scene as seen from the first light with cast_shadows set,
+ * the lit shaders compare against it through these uniforms:
+ *
+ * uniform mat4 light_space;
+ * uniform sampler2D shadow_map;
+ * uniform int shadow_light;         index in the Lights block, -1 for no shadows
+ * uniform float shadow_min_bias;
+ * uniform float shadow_max_bias;
+ * uniform int shadow_pcf_radius;
+ */
+pub struct ShadowMap {
+    framebuffer: FramebufferDescriptor,
+    depth_program: Program,
+    settings: ShadowSettings,
+    light_space: Mat4,
+    shadow_light: Option<usize>,
+}
+
+impl ShadowMap {
+    pub fn new(settings: ShadowSettings) -> Result<ShadowMap, String> {
+        if settings.resolution <= 0 {
+            return Err(format!("invalid shadow map resolution {}", settings.resolution));
+        }
+
+        let framebuffer =
+            FramebufferDescriptor::new_depth_only(settings.resolution, settings.resolution)?;
+        let depth_program = Program::from_files(SHADOW_DEPTH_VERTEX, SHADOW_DEPTH_FRAGMENT)?;
+
+        Ok(ShadowMap {
+            framebuffer: framebuffer,
+            depth_program: depth_program,
+            settings: settings,
+            light_space: Mat4::IDENTITY,
+            shadow_light: None,
+        })
+    }
+
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    // Renders every mesh of the scene into the depth texture. Leaves the window's
+    // framebuffer bound, the caller has to restore its viewport.
+    pub fn render(&mut self, lights: &[Light], scene: &SceneGraph) {
+        self.shadow_light = None;
+
+        for (light_idx, light) in lights.iter().enumerate() {
+            if !light.cast_shadows {
+                continue;
+            }
+            if let Some(light_space) = light_space_matrix(light, &self.settings) {
+                self.shadow_light = Some(light_idx);
+                self.light_space = light_space;
+                break;
+            }
+        }
+
+        if self.shadow_light.is_none() {
+            return;
+        }
+
+        self.framebuffer.bind();
+
+        unsafe {
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+            gl::UseProgram(self.depth_program.id);
+        }
+
+        self.depth_program.set_uniform("light_space", self.light_space);
+
+        for node in scene.iter() {
+            let model = match node.model.as_ref() {
+                Some(model) => model,
+                None => continue,
+            };
+
+            for (mesh, mesh_transform) in model.meshes.iter().zip(model.mesh_transforms()) {
+                self.depth_program
+                    .set_uniform("model", node.world_matrix() * mesh_transform);
+                mesh.bind_vao();
+                mesh.render();
+            }
+        }
+
+        FramebufferDescriptor::bind_default();
+    }
+
+    // Points a lit program at the shadow map, the program has to be in use.
+    // Programs without shadow uniforms are left alone.
+    pub fn apply(&self, program: &Program) {
+        if !program.has_uniform("shadow_light") {
+            return;
+        }
+
+        let shadow_light = match self.shadow_light {
+            Some(shadow_light) => shadow_light,
+            None => {
+                ShadowMap::apply_none(program);
+                return;
+            }
+        };
+
+        self.framebuffer.bind_depth_texture(SHADOW_MAP_TEXTURE_UNIT);
+
+        program.set_uniform("shadow_light", shadow_light as i32);
+        program.set_uniform("light_space", self.light_space);
+        program.set_uniform("shadow_map", SHADOW_MAP_TEXTURE_UNIT as i32);
+        program.set_uniform("shadow_min_bias", self.settings.min_bias);
+        program.set_uniform("shadow_max_bias", self.settings.max_bias);
+        program.set_uniform("shadow_pcf_radius", self.settings.pcf_radius);
+    }
+
+    // Turns shadows off in a lit program without a shadow map around, else
+    // shadow_light keeps what it was last set to while shadows were on
+    pub fn apply_none(program: &Program) {
+        if program.has_uniform("shadow_light") {
+            program.set_uniform("shadow_light", -1);
+        }
+    }
+}
@@ -0,0 +1,6 @@
+#version 330 core
+
+// Only the depth buffer is written
+void main()
+{
+}
@@ -0,0 +1,123 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// GL draw calls since take_draw_calls() was last called
+static DRAW_CALLS: AtomicU32 = AtomicU32::new(0);
+
+// Called next to every glDraw* call
+pub fn count_draw_call() {
+    DRAW_CALLS.fetch_add(1, Ordering::Relaxed);
+}
+
+// Returns the count and starts over from 0
+pub fn take_draw_calls() -> u32 {
+    DRAW_CALLS.swap(0, Ordering::Relaxed)
+}
+
+// Block characters from one eighth to full height
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// The last capacity frame times in seconds, oldest first
+pub struct FrameTimes {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl FrameTimes {
+    pub fn new(capacity: usize) -> FrameTimes {
+        FrameTimes {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&mut self, seconds: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(seconds.max(0.0));
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().sum::<f32>() / self.samples.len() as f32
+    }
+
+    pub fn max(&self) -> f32 {
+        self.samples.iter().cloned().fold(0.0, f32::max)
+    }
+
+    // Frames per second over the samples, 0 without any
+    pub fn fps(&self) -> f32 {
+        let average = self.average();
+        if average <= 0.0 {
+            return 0.0;
+        }
+        1.0 / average
+    }
+
+    // A bar per sample, full height at ceiling seconds and above
+    pub fn graph(&self, ceiling: f32) -> String {
+        self.samples
+            .iter()
+            .map(|seconds| {
+                let level = (seconds / ceiling.max(f32::EPSILON) * BARS.len() as f32).ceil() as usize;
+                BARS[level.clamp(1, BARS.len()) - 1]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_frame_times() {
+        let frame_times = FrameTimes::new(4);
+        assert_eq!(frame_times.average(), 0.0);
+        assert_eq!(frame_times.max(), 0.0);
+        assert_eq!(frame_times.fps(), 0.0);
+        assert_eq!(frame_times.graph(1.0), "");
+    }
+
+    #[test]
+    fn oldest_samples_are_dropped() {
+        let mut frame_times = FrameTimes::new(3);
+        for seconds in [0.5, 0.01, 0.02, 0.03] {
+            frame_times.push(seconds);
+        }
+        assert!((frame_times.average() - 0.02).abs() < 1e-6);
+        assert_eq!(frame_times.max(), 0.03);
+        assert!((frame_times.fps() - 50.0).abs() < 1e-3);
+
+        // Never below one sample, negative times count as 0
+        let mut frame_times = FrameTimes::new(0);
+        frame_times.push(0.1);
+        frame_times.push(-1.0);
+        assert_eq!(frame_times.average(), 0.0);
+        assert_eq!(frame_times.fps(), 0.0);
+    }
+
+    #[test]
+    fn graph_is_clamped() {
+        let mut frame_times = FrameTimes::new(5);
+        for seconds in [0.0, 0.01, 0.05, 0.1, 0.3] {
+            frame_times.push(seconds);
+        }
+        // Nothing under the lowest bar, nothing over the full one
+        assert_eq!(frame_times.graph(0.1), "▁▁▄██");
+        assert_eq!(frame_times.graph(0.0).chars().count(), 5);
+    }
+
+    #[test]
+    fn draw_calls_start_over() {
+        take_draw_calls();
+        count_draw_call();
+        count_draw_call();
+        assert_eq!(take_draw_calls(), 2);
+        assert_eq!(take_draw_calls(), 0);
+    }
+}
@@ -6,8 +6,52 @@ out vec4 color;
 uniform sampler2D text;
 uniform vec3 textColor;
 
+// Signed distance field glyphs, see sdf.rs
+uniform bool sdf;
+// How many font pixels the field reaches out from the edges
+uniform float sdfSpread;
+// Widths and offsets are in font pixels
+uniform vec4 outlineColor;
+uniform float outlineWidth;
+uniform vec4 shadowColor;
+uniform vec2 shadowOffset;
+uniform float shadowSoftness;
+
+// Font pixels out from the glyph's edge, negative inside
+float edgeDistance(vec2 coords)
+{
+    return (0.5 - texture(text, coords).r) * 2.0 * sdfSpread;
+}
+
+// a drawn over b, neither premultiplied
+vec4 over(vec4 a, vec4 b)
+{
+    float alpha = a.a + b.a * (1.0 - a.a);
+    vec3 rgb = (a.rgb * a.a + b.rgb * b.a * (1.0 - a.a)) / max(alpha, 0.0001);
+    return vec4(rgb, alpha);
+}
+
 void main()
 {
-    vec4 sampled = vec4(1.0, 1.0, 1.0, texture(text, TexCoords).r);
-    color = vec4(textColor, 1.0) * sampled;
-}
\ No newline at end of file
+    if (!sdf) {
+        vec4 sampled = vec4(1.0, 1.0, 1.0, texture(text, TexCoords).r);
+        color = vec4(textColor, 1.0) * sampled;
+        return;
+    }
+
+    float edge = edgeDistance(TexCoords);
+    // About one screen pixel, however much the text is scaled
+    float smoothing = max(fwidth(edge) * 0.7, 0.001);
+
+    float fill = 1.0 - smoothstep(-smoothing, smoothing, edge);
+    float outline = (1.0 - smoothstep(-smoothing, smoothing, edge - outlineWidth)) * outlineColor.a;
+    vec4 glyph = over(vec4(textColor, fill), vec4(outlineColor.rgb, outline));
+
+    // shadowOffset is right and down, the atlas has its top row first
+    vec2 shadowCoords = TexCoords - shadowOffset / vec2(textureSize(text, 0));
+    float shadowDistance = edgeDistance(shadowCoords) - outlineWidth;
+    float shadow = (1.0 - smoothstep(-smoothing - shadowSoftness, smoothing + shadowSoftness, shadowDistance))
+        * shadowColor.a;
+
+    color = over(glyph, vec4(shadowColor.rgb, shadow));
+}
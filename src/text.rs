@@ -1,34 +1,141 @@
 use std::collections::HashMap;
 use std::ffi::{CString, c_void};
 
-use freetype::freetype::{FT_Done_FreeType, FT_Library, FT_Face, FT_Init_FreeType, FT_New_Face, FT_Set_Pixel_Sizes, FT_LOAD_RENDER, FT_Load_Char, FT_Done_Face};
-use glam::{Mat4, IVec2, Vec3};
-use crate::gl::types::{GLint, GLuint};
-use crate::gl::{self, ARRAY_BUFFER};
+use freetype::freetype::{FT_Done_FreeType, FT_Library, FT_Face, FT_Init_FreeType, FT_New_Face, FT_Set_Pixel_Sizes, FT_LOAD_RENDER, FT_Load_Glyph, FT_Get_Char_Index, FT_Done_Face,
+    FT_Get_Kerning, FT_Kerning_Mode_, FT_Vector, FT_FACE_FLAG_KERNING};
+use glam::{Mat4, IVec2, Vec2, Vec3, Vec4};
+use crate::gl::types::GLuint;
+use crate::gl;
+
+use crate::atlas::{AtlasRect, ShelfPacker};
+use crate::sdf;
+use crate::stats;
+use crate::text_layout::{self, Line, TextLayout};
+use crate::{program::Program, uniform::UniformDescriptor};
+
+pub const DEFAULT_FONT: &str = "res/Hack-Regular.ttf";
+pub const DEFAULT_FONT_SIZE: u32 = 48;
+// Width and height of every atlas texture, a new one is made when one fills up
+const ATLAS_SIZE: u32 = 1024;
+const ATLAS_PADDING: u32 = 1;
+// How far (in font pixels) the distance fields of SDF fonts reach past the glyph edges
+pub const SDF_SPREAD: u32 = 8;
+
+// A font file at one pixel size, as returned by TextManager::load_font
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FontId(usize);
+
+struct Font {
+    face: FT_Face,
+    pixel_size: u32,
+    // Baseline to baseline in pixels
+    line_height: f32,
+    has_kerning: bool,
+    // Glyphs are signed distance fields
+    sdf: bool,
+}
+
+/*
+ * Outline and drop shadow around the glyphs of SDF fonts, sizes are in font
+ * pixels. Everything has to stay within SDF_SPREAD of the glyph edges: the
+ * outline width, and the shadow offset plus softness plus outline width.
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SdfStyle {
+    pub outline_color: Vec4,
+    pub outline_width: f32,
+    pub shadow_color: Vec4,
+    // Right and down
+    pub shadow_offset: Vec2,
+    pub shadow_softness: f32,
+}
+
+impl Default for SdfStyle {
+    fn default() -> SdfStyle {
+        SdfStyle {
+            outline_color: Vec4::ZERO,
+            outline_width: 0.0,
+            shadow_color: Vec4::ZERO,
+            shadow_offset: Vec2::ZERO,
+            shadow_softness: 0.0,
+        }
+    }
+}
 
-use crate::uniform::{UniformPackedParam, Uniform3FParam};
-use crate::{program::Program, uniform::{UniformDescriptor, Uniform4FVMatrix}};
+// One atlas texture and what's packed into it so far
+struct AtlasPage {
+    texture: GLuint,
+    packer: ShelfPacker,
+}
+
+#[derive(Clone, Copy)]
+struct Glyph {
+    // The font it came from, another one than asked for when it's a
+    // fallback, and its index there for kerning
+    owner: usize,
+    index: u32,
+    // Page and where on it, None for glyphs without pixels like space
+    location: Option<(usize, AtlasRect)>,
+    size: IVec2,
+    bearing: IVec2,
+    // In pixels
+    advance: f32,
+}
 
+/*
+ * Draws text with glyphs that get rasterized the first time they're used and
+ * packed into shared atlas textures. Codepoints a font doesn't have are taken
+ * from the other loaded fonts, in the order they were loaded, and drawn as the
+ * font's missing glyph box (glyph 0) when none of them has it either.
+ */
 pub struct TextManager {
     program: Program,
     text_uniform: UniformDescriptor,
     text_projection_uniform: UniformDescriptor,
     text_projection: Mat4,
-    characters: HashMap<char, Character>,
-    text_vao: gl::types::GLuint,
-    text_vbo: gl::types::GLuint,
+    screen_size: Vec2,
+    library: FT_Library,
+    fonts: Vec<Font>,
+    pages: Vec<AtlasPage>,
+    glyphs: HashMap<(FontId, char), Glyph>,
+    // Refilled by every render_text call
+    stream: TextMesh,
+    sdf_style: SdfStyle,
 }
 
-pub struct Character {
-    TextureID: u32,
-    Size: IVec2,
-    Bearing: IVec2,
-    Advance: u32,
+// A run of vertices that sample the same atlas page
+#[derive(Clone, Copy, PartialEq)]
+struct PageRange {
+    page: usize,
+    first: i32,
+    count: i32,
+}
+
+/*
+ * Laid out text in a vertex buffer of its own, for labels that stay the same
+ * from frame to frame. TextManager::update_text_mesh only lays it out and
+ * uploads it again when the text or its placement changed.
+ */
+pub struct TextMesh {
+    vao: gl::types::GLuint,
+    vbo: gl::types::GLuint,
+    // Floats the buffer has room for
+    capacity: usize,
+    ranges: Vec<PageRange>,
+    // What it was last laid out from
+    source: Option<(FontId, String, f32, f32, f32, TextLayout)>,
 }
 
 impl TextManager {
-    pub fn new(program: Program) -> TextManager {
-        let mut text_uniform = UniformDescriptor::new(
+    pub fn new(program: Program) -> Result<TextManager, String> {
+        let mut library: FT_Library = std::ptr::null_mut();
+        unsafe {
+            if FT_Init_FreeType(&mut library as *mut FT_Library) != 0 {
+                return Err("Failed initializing FreeType library".to_string());
+            }
+        }
+
+        let text_uniform = UniformDescriptor::new(
             program.id,
             "textColor"
         );
@@ -40,203 +147,674 @@ impl TextManager {
             "projection"
         );
 
-        let mut text_vbo = 0;
-        let mut text_vao = 0;
-
-        // For now we reserve enough memory when initiating the VBO so that we can later update the VBO's memory
-        // when rendering characters:
+        // disable byte alignment restriction
         unsafe {
-            gl::GenVertexArrays(1, &mut text_vao);
-            gl::GenBuffers(1, &mut text_vbo);
-            gl::BindVertexArray(text_vao);
-            gl::BindBuffer(gl::ARRAY_BUFFER, text_vbo);
-            gl::BufferData(gl::ARRAY_BUFFER,
-                (std::mem::size_of::<f32>() * 6 * 4) as isize,
-                std::ptr::null(),
-                gl::DYNAMIC_DRAW
-            );
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(
-                0,
-                4,
-                gl::FLOAT,
-                gl::FALSE,
-                (4 * std::mem::size_of::<f32>()) as i32,
-                0 as *const gl::types::GLvoid
-            );
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindVertexArray(0);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::UseProgram(program.id);
         }
 
-        TextManager { program: program,
+        text_proj_uniform.update(text_projection);
+
+        Ok(TextManager { program: program,
                       text_uniform: text_uniform,
-                      characters: HashMap::new(),
                       text_projection_uniform: text_proj_uniform,
                       text_projection: text_projection,
-                      text_vao: text_vao,
-                      text_vbo: text_vbo}
+                      screen_size: Vec2::new(1024.0, 768.0),
+                      library: library,
+                      fonts: Vec::new(),
+                      pages: Vec::new(),
+                      glyphs: HashMap::new(),
+                      stream: TextMesh::new(),
+                      sdf_style: SdfStyle::default()})
     }
 
-    pub fn init(&mut self) {
-        let mut ft: FT_Library = std::ptr::null_mut();
-        let mut face: FT_Face = std::ptr::null_mut();
+    // The same file can be loaded more than once at different sizes
+    pub fn load_font(&mut self, path: &str, pixel_size: u32) -> Result<FontId, String> {
+        self.add_font(path, pixel_size, false)
+    }
+
+    /*
+     * Loads a font whose glyphs are stored as signed distance fields, they stay
+     * sharp at any scale (pixel_size only sets how much detail they keep) and
+     * can have an outline and shadow, see SdfStyle.
+     */
+    pub fn load_sdf_font(&mut self, path: &str, pixel_size: u32) -> Result<FontId, String> {
+        self.add_font(path, pixel_size, true)
+    }
 
+    fn add_font(&mut self, path: &str, pixel_size: u32, sdf: bool) -> Result<FontId, String> {
+        if pixel_size == 0 {
+            return Err(format!("Font size of {} has to be above 0", path));
+        }
+
+        let font_path = match CString::new(path) {
+            Ok(font_path) => font_path,
+            Err(_) => return Err(format!("Invalid font path {}", path)),
+        };
+
+        let mut face: FT_Face = std::ptr::null_mut();
         unsafe {
-            let mut ret = FT_Init_FreeType(&mut ft as *mut FT_Library);
-            if ret != 0 {
-                eprintln!("ERROR_FREETYPE: Failed initializing FreeType library!");
-                std::process::exit(1);
+            if FT_New_Face(self.library, font_path.as_ptr(), 0, &mut face as *mut FT_Face) != 0 {
+                return Err(format!("Failed to load font {}", path));
             }
 
-            {
-                let font_path = CString::new("res/Hack-Regular.ttf").unwrap(); 
-                ret = FT_New_Face(ft,
-                    font_path.as_ptr(),
-                    0,
-                    &mut face as *mut FT_Face
-                );
-                if ret != 0 {
-                    eprintln!("ERROR::FREETYPE: Failed to load font");
-                    std::process::exit(1);
-                }
+            if FT_Set_Pixel_Sizes(face, 0, pixel_size) != 0 {
+                FT_Done_Face(face);
+                return Err(format!("Error setting size {} of font {}", pixel_size, path));
             }
+        }
 
-            ret = FT_Set_Pixel_Sizes(face, 0, 48);
-            if ret != 0 {
-                eprintln!("ERROR::FREETYPE: Error setting font size");
-                std::process::exit(1);
-            }
+        let (line_height, has_kerning) = unsafe {
+            (
+                (*(*face).size).metrics.height as f32 / 64.0,
+                (*face).face_flags as u64 & FT_FACE_FLAG_KERNING as u64 != 0,
+            )
+        };
+
+        self.fonts.push(Font {
+            face: face,
+            pixel_size: pixel_size,
+            line_height: line_height,
+            has_kerning: has_kerning,
+            sdf: sdf,
+        });
+
+        Ok(FontId(self.fonts.len() - 1))
+    }
+
+    // The first font loaded, what render_text uses
+    pub fn default_font(&self) -> Option<FontId> {
+        if self.fonts.is_empty() {
+            return None;
         }
+        Some(FontId(0))
+    }
 
-        // disable byte alignment restriction
+    pub fn sdf_style(&self) -> SdfStyle {
+        self.sdf_style
+    }
+
+    // Used by everything drawn with SDF fonts from now on
+    pub fn set_sdf_style(&mut self, style: SdfStyle) {
+        self.sdf_style = style;
+    }
+
+    // Baseline to baseline distance in pixels, before any scale
+    pub fn line_height(&self, font: FontId) -> f32 {
+        self.fonts[font.0].line_height
+    }
+
+    // Text positions are in units of a width x height screen, from its bottom left corner
+    pub fn set_screen_size(&mut self, width: f32, height: f32) {
+        self.text_projection = Mat4::orthographic_rh_gl(0.0, width, 0.0, height, -1.0, 1.0);
+        self.screen_size = Vec2::new(width, height);
+
+        self.use_text_program();
+        self.text_projection_uniform.update(self.text_projection);
+    }
+
+    pub fn screen_size(&self) -> Vec2 {
+        self.screen_size
+    }
+
+    pub fn use_text_program(&self) {
         unsafe {
-            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::UseProgram(self.program.id);
         }
+    }
 
-        unsafe {
-            let mut ret;
+    fn glyph(&mut self, font: FontId, c: char) -> Glyph {
+        if let Some(glyph) = self.glyphs.get(&(font, c)) {
+            return *glyph;
+        }
 
-            for c in 0..128u8 {
-                ret = FT_Load_Char(face, c as u64, FT_LOAD_RENDER as i32);
-                if ret != 0 {
-                    eprintln!("ERROR::FREETYPE: Error loading character");
-                    std::process::exit(1);
+        let glyph = match self.rasterize(font, c) {
+            Ok(glyph) => glyph,
+            Err(e) => {
+                eprintln!("WARNING: {}", e);
+                Glyph {
+                    owner: font.0,
+                    index: 0,
+                    location: None,
+                    size: IVec2::ZERO,
+                    bearing: IVec2::ZERO,
+                    advance: 0.0,
                 }
+            }
+        };
 
-                let mut texture: GLuint = 0;
-                gl::GenTextures(1, &mut texture as *mut u32);
-                gl::BindTexture(gl::TEXTURE_2D, texture);
-                gl::TexImage2D(
-                    gl::TEXTURE_2D,
-                    0,
-                    gl::RED as i32,
-                    (*(*face).glyph).bitmap.width as i32,
-                   (*(*face).glyph).bitmap.rows as i32,
-                    0,
-                    gl::RED,
-                    gl::UNSIGNED_BYTE,
-                    (*(*face).glyph).bitmap.buffer as *const c_void
-                );
+        self.glyphs.insert((font, c), glyph);
+        glyph
+    }
 
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+    fn rasterize(&mut self, font: FontId, c: char) -> Result<Glyph, String> {
+        let pixel_size = self.fonts[font.0].pixel_size;
+
+        // The requested font first, then the others as fallbacks
+        let candidates = std::iter::once(font.0).chain((0..self.fonts.len()).filter(|i| *i != font.0));
+        let mut found = None;
+        for i in candidates {
+            let index = unsafe { FT_Get_Char_Index(self.fonts[i].face, c as u64) };
+            if index != 0 {
+                found = Some((i, index));
+                break;
+            }
+        }
 
-                let character = Character {
-                    TextureID: texture,
-                    Size: IVec2::new((*(*face).glyph).bitmap.width as i32, (*(*face).glyph).bitmap.rows as i32),
-                    Bearing: IVec2::new((*(*face).glyph).bitmap_left,  (*(*face).glyph).bitmap_top),
-                    Advance: (*(*face).glyph).advance.x as u32
-                };
+        // Glyph 0 is the font's missing glyph
+        let (owner, index) = found.unwrap_or((font.0, 0));
+        let face = self.fonts[owner].face;
+        // Fallback fonts might be loaded at another size
+        let resize = self.fonts[owner].pixel_size != pixel_size;
 
-                self.characters.insert(c as char, character);
+        let (mut bitmap, mut size, mut bearing, advance) = unsafe {
+            if resize && FT_Set_Pixel_Sizes(face, 0, pixel_size) != 0 {
+                return Err(format!("Error setting font size {} for '{}'", pixel_size, c));
             }
 
-            ret = FT_Done_Face(face);
-            if ret != 0 {
-                eprintln!("ERROR::FREETYPE: Error freeing Face resources");
-                std::process::exit(1);
+            let ret = FT_Load_Glyph(face, index, FT_LOAD_RENDER as i32);
+
+            if resize {
+                FT_Set_Pixel_Sizes(face, 0, self.fonts[owner].pixel_size);
             }
-            ret = FT_Done_FreeType(ft);
+
             if ret != 0 {
-                eprintln!("ERROR::FREETYPE: Error freeing FreeType resources");
-                std::process::exit(1);
+                return Err(format!("Error loading character '{}'", c));
             }
+
+            let slot = &*(*face).glyph;
+            let width = slot.bitmap.width as usize;
+            let rows = slot.bitmap.rows as usize;
+            let pitch = slot.bitmap.pitch.unsigned_abs() as usize;
+
+            // Rows can be padded, the atlas wants them tightly packed
+            let mut bitmap = Vec::with_capacity(width * rows);
+            for row in 0..rows {
+                let start = slot.bitmap.buffer.add(row * pitch);
+                bitmap.extend_from_slice(std::slice::from_raw_parts(start, width));
+            }
+
+            (
+                bitmap,
+                IVec2::new(width as i32, rows as i32),
+                IVec2::new(slot.bitmap_left, slot.bitmap_top),
+                slot.advance.x as f32 / 64.0,
+            )
+        };
+
+        // The field reaches SDF_SPREAD past the bitmap on every side
+        if self.fonts[font.0].sdf && size.x > 0 && size.y > 0 {
+            let spread = SDF_SPREAD as i32;
+            bitmap = sdf::distance_field(&bitmap, size.x as usize, size.y as usize, SDF_SPREAD as usize);
+            size += IVec2::splat(2 * spread);
+            bearing += IVec2::new(-spread, spread);
+        }
+
+        let location = if size.x > 0 && size.y > 0 {
+            Some(self.upload(&bitmap, size.x as u32, size.y as u32, c)?)
+        } else {
+            None
+        };
+
+        Ok(Glyph {
+            owner: owner,
+            index: index,
+            location: location,
+            size: size,
+            bearing: bearing,
+            advance: advance,
+        })
+    }
+
+    // Packs the bitmap into the last page or a new one when it's full
+    fn upload(&mut self, bitmap: &[u8], width: u32, height: u32, c: char) -> Result<(usize, AtlasRect), String> {
+        let mut packed = self
+            .pages
+            .last_mut()
+            .and_then(|page| page.packer.pack(width, height))
+            .map(|rect| (self.pages.len() - 1, rect));
+
+        if packed.is_none() {
+            let mut page = AtlasPage::new();
+            packed = page.packer.pack(width, height).map(|rect| (self.pages.len(), rect));
+            if packed.is_none() {
+                return Err(format!(
+                    "character '{}' of {}x{} doesn't fit into a {}x{} glyph atlas",
+                    c, width, height, ATLAS_SIZE, ATLAS_SIZE
+                ));
+            }
+            self.pages.push(page);
         }
 
+        let (page, rect) = packed.unwrap();
         unsafe {
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::BindTexture(gl::TEXTURE_2D, self.pages[page].texture);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                rect.x as i32,
+                rect.y as i32,
+                rect.width as i32,
+                rect.height as i32,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                bitmap.as_ptr() as *const c_void
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
         }
 
+        Ok((page, rect))
+    }
+
+    // Draws text in the default font, does nothing when no font is loaded
+    pub fn render_text(&mut self, text: &str, x: f32, y: f32, scale: f32, color: Vec3) {
+        if let Some(font) = self.default_font() {
+            self.render_text_with_font(font, text, x, y, scale, &TextLayout::default(), color);
+        }
+    }
+
+    /*
+     * Lays the whole string out into one buffer and draws it with one call per
+     * atlas page it uses. (x, y) is where the first line's baseline starts,
+     * the lines below it follow the font's line height.
+     */
+    pub fn render_text_with_font(
+        &mut self,
+        font: FontId,
+        text: &str,
+        x: f32,
+        y: f32,
+        scale: f32,
+        layout: &TextLayout,
+        color: Vec3,
+    ) {
+        let (vertices, ranges) = self.layout(font, text, x, y, scale, layout);
+        self.stream.upload(&vertices, ranges);
+
+        self.text_uniform.update(color);
+        self.apply_font(font);
+        self.stream.draw(&self.pages);
+    }
+
+    // Lays the mesh out again if any of the arguments changed since last time
+    pub fn update_text_mesh(
+        &mut self,
+        mesh: &mut TextMesh,
+        font: FontId,
+        text: &str,
+        x: f32,
+        y: f32,
+        scale: f32,
+        layout: &TextLayout,
+    ) {
+        if let Some((old_font, old_text, old_x, old_y, old_scale, old_layout)) = &mesh.source {
+            if *old_font == font
+                && old_text == text
+                && *old_x == x
+                && *old_y == y
+                && *old_scale == scale
+                && old_layout == layout
+            {
+                return;
+            }
+        }
+
+        let (vertices, ranges) = self.layout(font, text, x, y, scale, layout);
+        mesh.upload(&vertices, ranges);
+        mesh.source = Some((font, text.to_string(), x, y, scale, layout.clone()));
+    }
+
+    pub fn render_text_mesh(&mut self, mesh: &TextMesh, color: Vec3) {
+        let font = match &mesh.source {
+            Some((font, ..)) => *font,
+            None => return,
+        };
+
+        self.text_uniform.update(color);
+        self.apply_font(font);
+        mesh.draw(&self.pages);
+    }
+
+    /*
+     * Draws text standing in the scene at position, turned to face the camera
+     * (a billboard) and line_height world units per line. position is where
+     * the first line's baseline starts, or its middle with TextAlign::Center.
+     * It's depth tested against the scene but doesn't write depth itself, so
+     * draw it after everything it can be behind. An SDF font keeps it sharp
+     * up close.
+     */
+    pub fn render_text_in_world(
+        &mut self,
+        font: FontId,
+        text: &str,
+        position: Vec3,
+        line_height: f32,
+        layout: &TextLayout,
+        color: Vec3,
+        view: Mat4,
+        projection: Mat4,
+    ) {
+        // The camera's right and up axes are the view matrix's first two rows
+        let right = view.row(0).truncate();
+        let up = view.row(1).truncate();
+        let forward = right.cross(up);
+
+        let units_per_pixel = line_height / self.fonts[font.0].line_height;
+        let billboard = Mat4::from_cols(
+            (right * units_per_pixel).extend(0.0),
+            (up * units_per_pixel).extend(0.0),
+            forward.extend(0.0),
+            position.extend(1.0),
+        );
+
+        let (vertices, ranges) = self.layout(font, text, 0.0, 0.0, 1.0, layout);
+        self.stream.upload(&vertices, ranges);
 
         self.use_text_program();
+        self.text_projection_uniform.update(projection * view * billboard);
+        self.text_uniform.update(color);
+        self.apply_font(font);
+
+        unsafe {
+            gl::DepthMask(gl::FALSE);
+        }
+        self.stream.draw(&self.pages);
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+        }
 
-        self.text_projection_uniform.update(UniformPackedParam::UniformMatrix4FV(
-            Uniform4FVMatrix(self.text_projection),
-        ));
+        self.text_projection_uniform.update(self.text_projection);
+    }
 
-        
+    // Tells the shader whether the font's glyphs are distance fields and how to style them
+    fn apply_font(&self, font: FontId) {
+        let sdf = self.fonts[font.0].sdf;
+        self.program.set_uniform("sdf", sdf);
+        if !sdf {
+            return;
+        }
+
+        let style = &self.sdf_style;
+        self.program.set_uniform("sdfSpread", SDF_SPREAD as f32);
+        self.program.set_uniform("outlineColor", style.outline_color);
+        self.program.set_uniform("outlineWidth", style.outline_width);
+        self.program.set_uniform("shadowColor", style.shadow_color);
+        self.program.set_uniform("shadowOffset", style.shadow_offset);
+        self.program.set_uniform("shadowSoftness", style.shadow_softness);
     }
 
-    pub fn use_text_program(&self) {
+    // Width of the widest line and height of all of them, as render_text_with_font would draw it
+    pub fn measure_text(&mut self, font: FontId, text: &str, scale: f32, layout: &TextLayout) -> Vec2 {
+        let lines = self.break_lines(font, text, scale, layout);
+        let width = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+
+        Vec2::new(width, lines.len() as f32 * self.line_advance(font, scale, layout))
+    }
+
+    fn line_advance(&self, font: FontId, scale: f32, layout: &TextLayout) -> f32 {
+        self.fonts[font.0].line_height * layout.line_spacing * scale
+    }
+
+    // Pixels to add to the advance of left when right follows it
+    fn kerning(&self, left: &Glyph, right: &Glyph) -> f32 {
+        let font = &self.fonts[left.owner];
+        if left.owner != right.owner || !font.has_kerning {
+            return 0.0;
+        }
+
+        let mut kerning = FT_Vector { x: 0, y: 0 };
         unsafe {
-            gl::UseProgram(self.program.id);
+            let ret = FT_Get_Kerning(
+                font.face,
+                left.index,
+                right.index,
+                FT_Kerning_Mode_::FT_KERNING_DEFAULT as u32,
+                &mut kerning as *mut FT_Vector,
+            );
+            if ret != 0 {
+                return 0.0;
+            }
         }
+
+        kerning.x as f32 / 64.0
     }
 
-    pub fn render_text(&mut self, text: String, mut x: f32, mut y: f32, scale: f32, color: Vec3) {
-        self.text_uniform.update(
-            UniformPackedParam::Uniform3F(
-                    Uniform3FParam(color.x, color.y, color.z)
-            )
-        );
+    // How far c moves the pen after previous
+    fn advance(&mut self, font: FontId, previous: Option<char>, c: char) -> f32 {
+        let glyph = self.glyph(font, c);
+        let kerning = match previous {
+            Some(previous) => {
+                let previous = self.glyph(font, previous);
+                self.kerning(&previous, &glyph)
+            }
+            None => 0.0,
+        };
+
+        glyph.advance + kerning
+    }
+
+    fn break_lines(&mut self, font: FontId, text: &str, scale: f32, layout: &TextLayout) -> Vec<Line> {
+        text_layout::break_lines(text, layout.max_width, |previous, c| {
+            self.advance(font, previous, c) * scale
+        })
+    }
+
+    // Quads of every glyph with pixels, grouped by the atlas page they're on
+    fn layout(
+        &mut self,
+        font: FontId,
+        text: &str,
+        x: f32,
+        y: f32,
+        scale: f32,
+        layout: &TextLayout,
+    ) -> (Vec<f32>, Vec<PageRange>) {
+        let mut per_page: Vec<Vec<f32>> = Vec::new();
+        let line_advance = self.line_advance(font, scale, layout);
+
+        for (line_number, line) in self.break_lines(font, text, scale, layout).iter().enumerate() {
+            let mut x = x + layout.align_offset(line.width);
+            let y = y - line_number as f32 * line_advance;
+            let mut previous = None;
+
+            for c in text[line.start..line.end].chars() {
+                let ch = self.glyph(font, c);
+                if let Some(previous) = previous {
+                    let previous = self.glyph(font, previous);
+                    x += self.kerning(&previous, &ch) * scale;
+                }
+                previous = Some(c);
+
+                if let Some((page, rect)) = ch.location {
+                    let xpos = x + ch.bearing.x as f32 * scale;
+                    let ypos = y - (ch.size.y - ch.bearing.y) as f32 * scale;
+
+                    let w = ch.size.x as f32 * scale;
+                    let h = ch.size.y as f32 * scale;
+
+                    // Where the glyph is in the atlas, its top row is at rect.y
+                    let u0 = rect.x as f32 / ATLAS_SIZE as f32;
+                    let v0 = rect.y as f32 / ATLAS_SIZE as f32;
+                    let u1 = (rect.x + rect.width) as f32 / ATLAS_SIZE as f32;
+                    let v1 = (rect.y + rect.height) as f32 / ATLAS_SIZE as f32;
+
+                    if per_page.len() <= page {
+                        per_page.resize(page + 1, Vec::new());
+                    }
+
+                    let vertices = &mut per_page[page];
+                    vertices.extend_from_slice(&[xpos,     ypos + h, u0, v0]);
+                    vertices.extend_from_slice(&[xpos,     ypos,     u0, v1]);
+                    vertices.extend_from_slice(&[xpos + w, ypos,     u1, v1]);
+
+                    vertices.extend_from_slice(&[xpos,     ypos + h, u0, v0]);
+                    vertices.extend_from_slice(&[xpos + w, ypos,     u1, v1]);
+                    vertices.extend_from_slice(&[xpos + w, ypos + h, u1, v0]);
+                }
+
+                // now advance cursors for next glyph
+                x += ch.advance * scale;
+            }
+        }
+
+        let mut vertices = Vec::new();
+        let mut ranges = Vec::new();
+        for (page, page_vertices) in per_page.into_iter().enumerate() {
+            if page_vertices.is_empty() {
+                continue;
+            }
+
+            ranges.push(PageRange {
+                page: page,
+                first: (vertices.len() / 4) as i32,
+                count: (page_vertices.len() / 4) as i32,
+            });
+            vertices.extend(page_vertices);
+        }
+
+        (vertices, ranges)
+    }
+
+}
+
+impl TextMesh {
+    pub fn new() -> TextMesh {
+        let mut vao = 0;
+        let mut vbo = 0;
+
         unsafe {
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindVertexArray(self.text_vao);
-        }
-
-        for c in text.chars() {
-            let ch = self.characters.get(&c).unwrap();
-
-            let xpos = x + ch.Bearing.x as f32 * scale;
-            let ypos = y - (ch.Size.y - ch.Bearing.y) as f32 * scale;
-
-            let w = ch.Size.x as f32 * scale;
-            let h = ch.Size.y as f32 * scale;
-            // Update VBO for each character
-            let mut vertices: Vec<f32> = Vec::with_capacity(6 * 4);
-            vertices.extend_from_slice(&[xpos,     ypos + h, 0.0, 0.0]);
-            vertices.extend_from_slice(&[xpos,     ypos,     0.0, 1.0]);
-            vertices.extend_from_slice(&[xpos + w, ypos,     1.0, 1.0]);
-
-            vertices.extend_from_slice(&[xpos,     ypos + h, 0.0, 0.0]);
-            vertices.extend_from_slice(&[xpos + w, ypos,     1.0, 1.0]);
-            vertices.extend_from_slice(&[xpos + w, ypos + h, 1.0, 0.0]);
-
-            unsafe {
-                // render glyph texture over quad
-                gl::BindTexture(gl::TEXTURE_2D, ch.TextureID);
-                // Update the content of the VBO memory
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.text_vbo);
-                gl::BufferSubData(gl::ARRAY_BUFFER,
-                    0,
-                    (vertices.len() * std::mem::size_of::<f32>()) as isize,
-                    vertices.as_ptr() as *const c_void
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            // <vec2 pos, vec2 tex>
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(
+                0,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                (4 * std::mem::size_of::<f32>()) as i32,
+                0 as *const gl::types::GLvoid
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+
+        TextMesh {
+            vao: vao,
+            vbo: vbo,
+            capacity: 0,
+            ranges: Vec::new(),
+            source: None,
+        }
+    }
+
+    // Reuses the buffer's memory when the vertices fit, otherwise grows it
+    fn upload(&mut self, vertices: &[f32], ranges: Vec<PageRange>) {
+        self.ranges = ranges;
+        if vertices.is_empty() {
+            return;
+        }
+
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            if vertices.len() > self.capacity {
+                self.capacity = vertices.len().next_power_of_two();
+                gl::BufferData(gl::ARRAY_BUFFER,
+                    (self.capacity * std::mem::size_of::<f32>()) as isize,
+                    std::ptr::null(),
+                    gl::DYNAMIC_DRAW
                 );
-                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-                // render quad
-                gl::DrawArrays(gl::TRIANGLES, 0, 6);
-                // now advance cursors for next glyph(note that advance is number of 1/64 pixels)
-                x += (ch.Advance >> 6) as f32 * scale; // bitshift by 6 to get value in pixels (2^6 = 64)
             }
+            gl::BufferSubData(gl::ARRAY_BUFFER,
+                0,
+                (vertices.len() * std::mem::size_of::<f32>()) as isize,
+                vertices.as_ptr() as *const c_void
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
+
+    // The text program has to be in use
+    fn draw(&self, pages: &[AtlasPage]) {
+        if self.ranges.is_empty() {
+            return;
         }
 
         unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindVertexArray(self.vao);
+
+            for range in self.ranges.iter() {
+                gl::BindTexture(gl::TEXTURE_2D, pages[range.page].texture);
+                gl::DrawArrays(gl::TRIANGLES, range.first, range.count);
+                stats::count_draw_call();
+            }
+
             gl::BindVertexArray(0);
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
     }
+}
+
+impl Drop for TextMesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
 
-}
\ No newline at end of file
+impl AtlasPage {
+    fn new() -> AtlasPage {
+        let mut texture: GLuint = 0;
+        // Cleared so filtering at glyph edges only picks up empty texels
+        let empty = vec![0u8; (ATLAS_SIZE * ATLAS_SIZE) as usize];
+
+        unsafe {
+            gl::GenTextures(1, &mut texture as *mut u32);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RED as i32,
+                ATLAS_SIZE as i32,
+                ATLAS_SIZE as i32,
+                0,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                empty.as_ptr() as *const c_void
+            );
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        AtlasPage {
+            texture: texture,
+            packer: ShelfPacker::new(ATLAS_SIZE, ATLAS_SIZE, ATLAS_PADDING),
+        }
+    }
+}
+
+impl Drop for TextManager {
+    fn drop(&mut self) {
+        unsafe {
+            for page in self.pages.iter() {
+                gl::DeleteTextures(1, &page.texture);
+            }
+
+            for font in self.fonts.iter() {
+                FT_Done_Face(font.face);
+            }
+            FT_Done_FreeType(self.library);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/*
+ * How text is broken into lines and placed. Without max_width lines only
+ * break at '\n' and are aligned around the x they're drawn at, with it they
+ * also wrap between words to stay narrower and are aligned inside the
+ * x to x + max_width box. line_spacing multiplies the font's line height.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextLayout {
+    pub max_width: Option<f32>,
+    pub align: TextAlign,
+    pub line_spacing: f32,
+}
+
+impl Default for TextLayout {
+    fn default() -> TextLayout {
+        TextLayout {
+            max_width: None,
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+impl TextLayout {
+    // How far right of x a line width wide starts
+    pub fn align_offset(&self, width: f32) -> f32 {
+        match (self.align, self.max_width) {
+            (TextAlign::Left, _) => 0.0,
+            (TextAlign::Center, Some(max_width)) => (max_width - width) * 0.5,
+            (TextAlign::Right, Some(max_width)) => max_width - width,
+            (TextAlign::Center, None) => -width * 0.5,
+            (TextAlign::Right, None) => -width,
+        }
+    }
+}
+
+// A line of the text, start..end are byte offsets into it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Line {
+    pub start: usize,
+    pub end: usize,
+    pub width: f32,
+}
+
+/*
+ * Breaks text into lines at every '\n' and, given a max_width, between words
+ * where a line would get wider than that. A word that's wider on its own is
+ * broken between characters. advance(previous, c) is how far c moves the pen
+ * after previous (None at the start of a line), kerning included. Whitespace
+ * at the end of a line doesn't count into its width and wrapped lines don't
+ * start with whitespace.
+ */
+pub fn break_lines<F>(text: &str, max_width: Option<f32>, mut advance: F) -> Vec<Line>
+where
+    F: FnMut(Option<char>, char) -> f32,
+{
+    let mut lines = Vec::new();
+    let mut offset = 0;
+
+    for paragraph in text.split('\n') {
+        break_paragraph(paragraph, offset, max_width, &mut advance, &mut lines);
+        offset += paragraph.len() + 1;
+    }
+
+    lines
+}
+
+fn break_paragraph<F>(
+    paragraph: &str,
+    offset: usize,
+    max_width: Option<f32>,
+    advance: &mut F,
+    lines: &mut Vec<Line>,
+) where
+    F: FnMut(Option<char>, char) -> f32,
+{
+    let chars: Vec<(usize, char)> = paragraph.char_indices().collect();
+    let byte_offset = |i: usize| offset + chars.get(i).map_or(paragraph.len(), |(byte, _)| *byte);
+
+    let mut line_start = 0;
+    let mut width = 0.0;
+    // Width up to the last character that isn't whitespace
+    let mut visible_width = 0.0;
+    let mut previous: Option<char> = None;
+    // Where the line can be broken: the end of the last word, its width
+    // and where the next line would start after the whitespace
+    let mut last_break: Option<(usize, f32, usize)> = None;
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        let next_width = width + advance(previous, c);
+
+        if c.is_whitespace() {
+            if i > line_start && !previous.map_or(true, |p| p.is_whitespace()) {
+                last_break = Some((i, visible_width, i + 1));
+            } else if let Some((_, _, next)) = last_break.as_mut() {
+                *next = i + 1;
+            }
+        } else if max_width.map_or(false, |max_width| next_width > max_width) && i > line_start {
+            let (end, line_width, next) = last_break.unwrap_or((i, visible_width, i));
+            lines.push(Line {
+                start: byte_offset(line_start),
+                end: byte_offset(end),
+                width: line_width,
+            });
+
+            line_start = next;
+            width = 0.0;
+            visible_width = 0.0;
+            previous = None;
+            last_break = None;
+            // Measured again from the start of the new line
+            i = line_start;
+            continue;
+        } else {
+            visible_width = next_width;
+        }
+
+        width = next_width;
+        previous = Some(c);
+        i += 1;
+    }
+
+    // Without the whitespace at its end
+    let mut end = chars.len();
+    while end > line_start && chars[end - 1].1.is_whitespace() {
+        end -= 1;
+    }
+
+    lines.push(Line {
+        start: byte_offset(line_start),
+        end: byte_offset(end),
+        width: visible_width,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every character one unit wide
+    fn lines(text: &str, max_width: Option<f32>) -> Vec<(&str, f32)> {
+        break_lines(text, max_width, |_, _| 1.0)
+            .iter()
+            .map(|line| (&text[line.start..line.end], line.width))
+            .collect()
+    }
+
+    #[test]
+    fn wraps_between_words() {
+        assert_eq!(
+            lines("hello world foo", Some(8.0)),
+            vec![("hello", 5.0), ("world", 5.0), ("foo", 3.0)]
+        );
+        assert_eq!(lines("hello world foo", None), vec![("hello world foo", 15.0)]);
+        assert_eq!(lines("", Some(8.0)), vec![("", 0.0)]);
+    }
+
+    #[test]
+    fn trailing_whitespace_is_dropped() {
+        assert_eq!(lines("hello   ", None), vec![("hello", 5.0)]);
+        assert_eq!(lines("hello \t", Some(5.0)), vec![("hello", 5.0)]);
+        // Leading whitespace of the first line stays
+        assert_eq!(
+            lines("  indented word here", Some(10.0)),
+            vec![("  indented", 10.0), ("word here", 9.0)]
+        );
+    }
+
+    #[test]
+    fn runs_of_spaces_break_once() {
+        assert_eq!(
+            lines("trailing   spaces   here", Some(9.0)),
+            vec![("trailing", 8.0), ("spaces", 6.0), ("here", 4.0)]
+        );
+        // Kept inside a line
+        assert_eq!(lines("a   b", Some(9.0)), vec![("a   b", 5.0)]);
+    }
+
+    #[test]
+    fn long_words_break_between_characters() {
+        assert_eq!(
+            lines("abcdefghij", Some(3.0)),
+            vec![("abc", 3.0), ("def", 3.0), ("ghi", 3.0), ("j", 1.0)]
+        );
+        assert_eq!(lines("ab cdefgh", Some(4.0)), vec![("ab", 2.0), ("cdef", 4.0), ("gh", 2.0)]);
+        // Always at least a character per line
+        assert_eq!(lines("abc", Some(0.5)), vec![("a", 1.0), ("b", 1.0), ("c", 1.0)]);
+    }
+
+    #[test]
+    fn newlines_give_empty_lines() {
+        let text = "a\n\nb  \n";
+        let breaks = break_lines(text, Some(8.0), |_, _| 1.0);
+        let ranges: Vec<_> = breaks.iter().map(|line| (line.start, line.end)).collect();
+        assert_eq!(ranges, vec![(0, 1), (2, 2), (3, 4), (7, 7)]);
+        assert_eq!(breaks[1].width, 0.0);
+    }
+
+    #[test]
+    fn offsets_are_bytes() {
+        let text = "héllo wörld";
+        let breaks = break_lines(text, Some(5.0), |_, _| 1.0);
+        let ranges: Vec<_> = breaks.iter().map(|line| (line.start, line.end, line.width)).collect();
+        assert_eq!(ranges, vec![(0, 6, 5.0), (7, 13, 5.0)]);
+        assert_eq!(&text[breaks[1].start..breaks[1].end], "wörld");
+    }
+
+    #[test]
+    fn advance_starts_over_on_every_line() {
+        let mut pairs = Vec::new();
+        break_lines("ab cd\nef", Some(3.0), |previous, c| {
+            pairs.push((previous, c));
+            1.0
+        });
+        assert_eq!(pairs[0], (None, 'a'));
+        assert_eq!(pairs[1], (Some('a'), 'b'));
+        assert!(pairs.contains(&(None, 'c')));
+        assert!(pairs.contains(&(None, 'e')));
+    }
+
+    #[test]
+    fn align_offsets() {
+        let layout = |align, max_width| TextLayout {
+            max_width: max_width,
+            align: align,
+            ..TextLayout::default()
+        };
+
+        assert_eq!(layout(TextAlign::Left, None).align_offset(6.0), 0.0);
+        assert_eq!(layout(TextAlign::Left, Some(10.0)).align_offset(6.0), 0.0);
+        assert_eq!(layout(TextAlign::Center, Some(10.0)).align_offset(6.0), 2.0);
+        assert_eq!(layout(TextAlign::Right, Some(10.0)).align_offset(6.0), 4.0);
+        // Around x without a box
+        assert_eq!(layout(TextAlign::Center, None).align_offset(6.0), -3.0);
+        assert_eq!(layout(TextAlign::Right, None).align_offset(6.0), -6.0);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::gl;
 use stb_image::stb_image::bindgen::*;
-use std::ffi::{c_int, c_void, CString};
+use std::ffi::{c_int, c_void, CStr, CString};
 
+// Copies share the same GL texture
+#[derive(Clone)]
 pub struct TextureDescriptor {
     texture_id: gl::types::GLuint,
     texture_shader_handle: gl::types::GLint,
@@ -49,19 +51,22 @@ impl TextureDescriptor {
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
 
             stbi_set_flip_vertically_on_load(1);
+            // Converted to the channels of format, whatever the file stores
             let buffer = stbi_load(
                 path_string.as_ptr(),
                 &mut width,
                 &mut height,
                 &mut nr_channels,
-                0,
+                channel_count(format),
             );
 
             if !buffer.is_null() {
+                // Rows of RGB and single channel images are not 4 byte aligned
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
                 gl::TexImage2D(
                     gl::TEXTURE_2D,
                     0,
-                    gl::RGB as i32,
+                    format as i32,
                     width,
                     height,
                     0,
@@ -78,6 +83,135 @@ impl TextureDescriptor {
         texture_desc
     }
 
+    // Uploads already decoded pixels without flipping them,
+    // so the first row ends up at texture coordinate t = 0
+    pub fn from_pixels(
+        bound_program_id: gl::types::GLuint,
+        shader_handle_name: &str,
+        width: i32,
+        height: i32,
+        format: gl::types::GLenum,
+        pixels: &[u8],
+    ) -> TextureDescriptor {
+        let mut texture_id: gl::types::GLuint = 0;
+        let texture_shader_handle;
+        unsafe {
+            texture_shader_handle = gl::GetUniformLocation(
+                bound_program_id,
+                CString::new(shader_handle_name.to_string())
+                    .unwrap()
+                    .as_ptr(),
+            );
+            gl::GenTextures(1, &mut texture_id);
+
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                gl::LINEAR_MIPMAP_LINEAR as i32,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+
+            // Rows of RGB and single channel images are not 4 byte aligned
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format as i32,
+                width,
+                height,
+                0,
+                format,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr() as *const c_void,
+            );
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+        }
+
+        TextureDescriptor {
+            texture_id: texture_id,
+            texture_shader_handle: texture_shader_handle,
+        }
+    }
+
+    // Decodes an image file that's already in memory, like a texture
+    // embedded in a model, flipped the same way new() flips files
+    pub fn from_memory(
+        bound_program_id: gl::types::GLuint,
+        shader_handle_name: &str,
+        bytes: &[u8],
+    ) -> Result<TextureDescriptor, String> {
+        let mut width: c_int = 0;
+        let mut height: c_int = 0;
+        let mut nr_channels: c_int = 0;
+
+        let buffer = unsafe {
+            stbi_set_flip_vertically_on_load(1);
+            stbi_load_from_memory(
+                bytes.as_ptr(),
+                bytes.len() as c_int,
+                &mut width,
+                &mut height,
+                &mut nr_channels,
+                0,
+            )
+        };
+        if buffer.is_null() {
+            return Err(format!("Failed to decode texture: {}", failure_reason()));
+        }
+
+        let format = match nr_channels {
+            1 => gl::RED,
+            2 => gl::RG,
+            3 => gl::RGB,
+            _ => gl::RGBA,
+        };
+
+        let texture = unsafe {
+            let pixels = std::slice::from_raw_parts(buffer, (width * height * nr_channels) as usize);
+            let texture =
+                TextureDescriptor::from_pixels(bound_program_id, shader_handle_name, width, height, format, pixels);
+            stbi_image_free(buffer as *mut c_void);
+            texture
+        };
+
+        Ok(texture)
+    }
+
+    // Same as new(), but picks the format from the number of channels
+    // stored in the image file instead of taking it from the caller
+    pub fn new_detect_format(
+        bound_program_id: gl::types::GLuint,
+        shader_handle_name: &str,
+        path: &str,
+    ) -> Result<TextureDescriptor, String> {
+        let mut width: c_int = 0;
+        let mut height: c_int = 0;
+        let mut nr_channels: c_int = 0;
+        let path_string = match CString::new(path) {
+            Ok(path_string) => path_string,
+            Err(_) => return Err(format!("Invalid texture path {}", path)),
+        };
+
+        let found = unsafe { stbi_info(path_string.as_ptr(), &mut width, &mut height, &mut nr_channels) };
+        if found == 0 {
+            return Err(format!("Failed to read texture {}: {}", path, failure_reason()));
+        }
+
+        let format = match nr_channels {
+            1 => gl::RED,
+            2 => gl::RG,
+            3 => gl::RGB,
+            4 => gl::RGBA,
+            _ => return Err(format!("Texture {} has {} channels", path, nr_channels)),
+        };
+
+        Ok(TextureDescriptor::new(bound_program_id, shader_handle_name, path, format))
+    }
+
     pub fn set_active_texture(&self, idx: u32) {
         unsafe {
             gl::ActiveTexture(gl::TEXTURE0 + idx);
@@ -86,3 +220,23 @@ impl TextureDescriptor {
         }
     }
 }
+
+fn channel_count(format: gl::types::GLenum) -> c_int {
+    match format {
+        gl::RED => 1,
+        gl::RG => 2,
+        gl::RGBA => 4,
+        _ => 3,
+    }
+}
+
+// Why the last stb_image call failed
+fn failure_reason() -> String {
+    unsafe {
+        let reason = stbi_failure_reason();
+        if reason.is_null() {
+            return "unknown error".to_string();
+        }
+        CStr::from_ptr(reason).to_string_lossy().into_owned()
+    }
+}
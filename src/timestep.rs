@@ -0,0 +1,95 @@
+/*
+ * Runs the simulation in steps of a fixed length no matter how long frames
+ * take. Every frame adds the time it took and gets back how many steps to run,
+ * what's left over is how far the frame is into the next step (alpha), for
+ * blending the previous and current simulation state when drawing.
+ */
+pub struct FixedTimestep {
+    step: f64,
+    accumulator: f64,
+    // After a long stall (a breakpoint, dragging the window) the time that
+    // doesn't fit into this many steps is dropped instead of caught up on
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    pub fn new(updates_per_second: f64) -> FixedTimestep {
+        FixedTimestep {
+            step: 1.0 / updates_per_second,
+            accumulator: 0.0,
+            max_steps: 8,
+        }
+    }
+
+    // Seconds per step
+    pub fn step(&self) -> f32 {
+        self.step as f32
+    }
+
+    // Adds frame_time seconds, returns how many steps to run now
+    pub fn advance(&mut self, frame_time: f64) -> u32 {
+        self.accumulator += frame_time.max(0.0);
+
+        let mut steps = (self.accumulator / self.step).floor() as u32;
+        if steps > self.max_steps {
+            steps = self.max_steps;
+            self.accumulator = self.accumulator % self.step;
+        } else {
+            self.accumulator -= steps as f64 * self.step;
+        }
+
+        steps
+    }
+
+    // 0 right after a step up to 1 just before the next one
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Quarter second steps add up without rounding
+    fn quarter_steps() -> FixedTimestep {
+        FixedTimestep::new(4.0)
+    }
+
+    #[test]
+    fn exact_multiple_of_the_step() {
+        let mut timestep = quarter_steps();
+        assert_eq!(timestep.step(), 0.25);
+        assert_eq!(timestep.advance(0.5), 2);
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(0.25), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn remainder_carries_over() {
+        let mut timestep = quarter_steps();
+        assert_eq!(timestep.advance(0.3125), 1);
+        assert_eq!(timestep.alpha(), 0.25);
+
+        assert_eq!(timestep.advance(0.125), 0);
+        assert_eq!(timestep.alpha(), 0.75);
+        assert_eq!(timestep.advance(0.125), 1);
+        assert_eq!(timestep.alpha(), 0.25);
+
+        // Time doesn't run backwards
+        assert_eq!(timestep.advance(-1.0), 0);
+        assert_eq!(timestep.alpha(), 0.25);
+    }
+
+    #[test]
+    fn long_stall_is_clamped() {
+        let mut timestep = quarter_steps();
+        assert_eq!(timestep.advance(10.0625), 8);
+        // Only the part of a step is kept, not the 32 steps behind
+        assert_eq!(timestep.alpha(), 0.25);
+        assert_eq!(timestep.advance(0.0), 0);
+        assert_eq!(timestep.advance(0.1875), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
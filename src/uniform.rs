@@ -4,59 +4,339 @@ use std::ffi::CString;
 
 // Every uniform is associated with a program
 pub struct UniformDescriptor {
+    name: String,
     uniform_shader_handle: gl::types::GLint,
 }
 
-pub struct Uniform3FParam(pub f32, pub f32, pub f32);
+/*
+ * A value for one uniform (or uniform array), in the glam type matching the
+ * GLSL one. glam has no non-square matrices, those are given as their columns:
+ * a mat2x3 (2 columns of 3 rows) is [Vec3; 2], a mat4x3 [Vec3; 4] and so on.
+ */
+pub enum UniformPackedParam {
+    Uniform1F(f32),
+    Uniform2F(Vec2),
+    Uniform3F(Vec3),
+    Uniform4F(Vec4),
+    Uniform1I(i32),
+    Uniform2I(IVec2),
+    Uniform3I(IVec3),
+    Uniform4I(IVec4),
+    Uniform1UI(u32),
+    Uniform2UI(UVec2),
+    Uniform3UI(UVec3),
+    Uniform4UI(UVec4),
+    // GLSL bools are set through the integer entry points
+    Uniform1B(bool),
+    UniformMatrix2FV(Mat2),
+    UniformMatrix3FV(Mat3),
+    UniformMatrix4FV(Mat4),
+    UniformMatrix2x3FV([Vec3; 2]),
+    UniformMatrix3x2FV([Vec2; 3]),
+    UniformMatrix2x4FV([Vec4; 2]),
+    UniformMatrix4x2FV([Vec2; 4]),
+    UniformMatrix3x4FV([Vec4; 3]),
+    UniformMatrix4x3FV([Vec3; 4]),
+    /*
+     * Arrays like "uniform vec3 lights[8]", uploaded starting at the location
+     * of the first element. Shorter arrays only update the first elements.
+     */
+    Uniform1FArray(Vec<f32>),
+    Uniform2FArray(Vec<Vec2>),
+    Uniform3FArray(Vec<Vec3>),
+    Uniform4FArray(Vec<Vec4>),
+    Uniform1IArray(Vec<i32>),
+    Uniform2IArray(Vec<IVec2>),
+    Uniform3IArray(Vec<IVec3>),
+    Uniform4IArray(Vec<IVec4>),
+    Uniform1UIArray(Vec<u32>),
+    Uniform2UIArray(Vec<UVec2>),
+    Uniform3UIArray(Vec<UVec3>),
+    Uniform4UIArray(Vec<UVec4>),
+    Uniform1BArray(Vec<bool>),
+    UniformMatrix2FVArray(Vec<Mat2>),
+    UniformMatrix3FVArray(Vec<Mat3>),
+    UniformMatrix4FVArray(Vec<Mat4>),
+    UniformMatrix2x3FVArray(Vec<[Vec3; 2]>),
+    UniformMatrix3x2FVArray(Vec<[Vec2; 3]>),
+    UniformMatrix2x4FVArray(Vec<[Vec4; 2]>),
+    UniformMatrix4x2FVArray(Vec<[Vec2; 4]>),
+    UniformMatrix3x4FVArray(Vec<[Vec4; 3]>),
+    UniformMatrix4x3FVArray(Vec<[Vec3; 4]>),
+}
 
-pub struct Uniform1IParam(pub i32);
+// Components of every value one after the other, the way the *v entry points take them
+fn flatten<T, C: Copy, const N: usize>(values: &[T], components: impl Fn(&T) -> [C; N]) -> Vec<C> {
+    values.iter().flat_map(components).collect()
+}
 
-pub struct Uniform1FParam(pub f32);
+// Columns of a non-square matrix (or of several) one after the other
+fn flatten_columns<const N: usize, const C: usize>(matrices: &[[impl Copy + Into<[f32; N]>; C]]) -> Vec<f32> {
+    matrices
+        .iter()
+        .flat_map(|columns| columns.iter().flat_map(|column| (*column).into()))
+        .collect()
+}
 
-pub struct Uniform4FVMatrix(pub Mat4);
+impl UniformPackedParam {
+    // Writes the value to a uniform location of the program currently in use
+    pub fn upload(&self, location: gl::types::GLint) {
+        use UniformPackedParam::*;
 
-pub enum UniformPackedParam {
-    Uniform3F(Uniform3FParam),
-    Uniform1I(Uniform1IParam),
-    Uniform1F(Uniform1FParam),
-    UniformMatrix4FV(Uniform4FVMatrix),
+        unsafe {
+            match self {
+                Uniform1F(value) => gl::Uniform1f(location, *value),
+                Uniform2F(value) => gl::Uniform2f(location, value.x, value.y),
+                Uniform3F(value) => gl::Uniform3f(location, value.x, value.y, value.z),
+                Uniform4F(value) => gl::Uniform4f(location, value.x, value.y, value.z, value.w),
+                Uniform1I(value) => gl::Uniform1i(location, *value),
+                Uniform2I(value) => gl::Uniform2i(location, value.x, value.y),
+                Uniform3I(value) => gl::Uniform3i(location, value.x, value.y, value.z),
+                Uniform4I(value) => gl::Uniform4i(location, value.x, value.y, value.z, value.w),
+                Uniform1UI(value) => gl::Uniform1ui(location, *value),
+                Uniform2UI(value) => gl::Uniform2ui(location, value.x, value.y),
+                Uniform3UI(value) => gl::Uniform3ui(location, value.x, value.y, value.z),
+                Uniform4UI(value) => gl::Uniform4ui(location, value.x, value.y, value.z, value.w),
+                Uniform1B(value) => gl::Uniform1i(location, *value as i32),
+                UniformMatrix2FV(value) => {
+                    gl::UniformMatrix2fv(location, 1, gl::FALSE, value.to_cols_array().as_ptr())
+                }
+                UniformMatrix3FV(value) => {
+                    gl::UniformMatrix3fv(location, 1, gl::FALSE, value.to_cols_array().as_ptr())
+                }
+                UniformMatrix4FV(value) => {
+                    gl::UniformMatrix4fv(location, 1, gl::FALSE, value.to_cols_array().as_ptr())
+                }
+                UniformMatrix2x3FV(columns) => {
+                    gl::UniformMatrix2x3fv(location, 1, gl::FALSE, flatten_columns(&[*columns]).as_ptr())
+                }
+                UniformMatrix3x2FV(columns) => {
+                    gl::UniformMatrix3x2fv(location, 1, gl::FALSE, flatten_columns(&[*columns]).as_ptr())
+                }
+                UniformMatrix2x4FV(columns) => {
+                    gl::UniformMatrix2x4fv(location, 1, gl::FALSE, flatten_columns(&[*columns]).as_ptr())
+                }
+                UniformMatrix4x2FV(columns) => {
+                    gl::UniformMatrix4x2fv(location, 1, gl::FALSE, flatten_columns(&[*columns]).as_ptr())
+                }
+                UniformMatrix3x4FV(columns) => {
+                    gl::UniformMatrix3x4fv(location, 1, gl::FALSE, flatten_columns(&[*columns]).as_ptr())
+                }
+                UniformMatrix4x3FV(columns) => {
+                    gl::UniformMatrix4x3fv(location, 1, gl::FALSE, flatten_columns(&[*columns]).as_ptr())
+                }
+                Uniform1FArray(values) => gl::Uniform1fv(location, values.len() as i32, values.as_ptr()),
+                Uniform2FArray(values) => {
+                    gl::Uniform2fv(location, values.len() as i32, flatten(values, Vec2::to_array).as_ptr())
+                }
+                Uniform3FArray(values) => {
+                    gl::Uniform3fv(location, values.len() as i32, flatten(values, Vec3::to_array).as_ptr())
+                }
+                Uniform4FArray(values) => {
+                    gl::Uniform4fv(location, values.len() as i32, flatten(values, Vec4::to_array).as_ptr())
+                }
+                Uniform1IArray(values) => gl::Uniform1iv(location, values.len() as i32, values.as_ptr()),
+                Uniform2IArray(values) => {
+                    gl::Uniform2iv(location, values.len() as i32, flatten(values, IVec2::to_array).as_ptr())
+                }
+                Uniform3IArray(values) => {
+                    gl::Uniform3iv(location, values.len() as i32, flatten(values, IVec3::to_array).as_ptr())
+                }
+                Uniform4IArray(values) => {
+                    gl::Uniform4iv(location, values.len() as i32, flatten(values, IVec4::to_array).as_ptr())
+                }
+                Uniform1UIArray(values) => gl::Uniform1uiv(location, values.len() as i32, values.as_ptr()),
+                Uniform2UIArray(values) => {
+                    gl::Uniform2uiv(location, values.len() as i32, flatten(values, UVec2::to_array).as_ptr())
+                }
+                Uniform3UIArray(values) => {
+                    gl::Uniform3uiv(location, values.len() as i32, flatten(values, UVec3::to_array).as_ptr())
+                }
+                Uniform4UIArray(values) => {
+                    gl::Uniform4uiv(location, values.len() as i32, flatten(values, UVec4::to_array).as_ptr())
+                }
+                Uniform1BArray(values) => {
+                    let ints: Vec<i32> = values.iter().map(|value| *value as i32).collect();
+                    gl::Uniform1iv(location, values.len() as i32, ints.as_ptr())
+                }
+                UniformMatrix2FVArray(values) => gl::UniformMatrix2fv(
+                    location,
+                    values.len() as i32,
+                    gl::FALSE,
+                    flatten(values, Mat2::to_cols_array).as_ptr(),
+                ),
+                UniformMatrix3FVArray(values) => gl::UniformMatrix3fv(
+                    location,
+                    values.len() as i32,
+                    gl::FALSE,
+                    flatten(values, Mat3::to_cols_array).as_ptr(),
+                ),
+                UniformMatrix4FVArray(values) => gl::UniformMatrix4fv(
+                    location,
+                    values.len() as i32,
+                    gl::FALSE,
+                    flatten(values, Mat4::to_cols_array).as_ptr(),
+                ),
+                UniformMatrix2x3FVArray(values) => {
+                    gl::UniformMatrix2x3fv(location, values.len() as i32, gl::FALSE, flatten_columns(values).as_ptr())
+                }
+                UniformMatrix3x2FVArray(values) => {
+                    gl::UniformMatrix3x2fv(location, values.len() as i32, gl::FALSE, flatten_columns(values).as_ptr())
+                }
+                UniformMatrix2x4FVArray(values) => {
+                    gl::UniformMatrix2x4fv(location, values.len() as i32, gl::FALSE, flatten_columns(values).as_ptr())
+                }
+                UniformMatrix4x2FVArray(values) => {
+                    gl::UniformMatrix4x2fv(location, values.len() as i32, gl::FALSE, flatten_columns(values).as_ptr())
+                }
+                UniformMatrix3x4FVArray(values) => {
+                    gl::UniformMatrix3x4fv(location, values.len() as i32, gl::FALSE, flatten_columns(values).as_ptr())
+                }
+                UniformMatrix4x3FVArray(values) => {
+                    gl::UniformMatrix4x3fv(location, values.len() as i32, gl::FALSE, flatten_columns(values).as_ptr())
+                }
+            }
+        }
+    }
+}
+
+/*
+ * Conversions from plain Rust and glam values, so call sites can write
+ * program.set_uniform("model", matrix) instead of wrapping the value
+ * in its variant. Non-square matrices have none, [Vec3; 4] could as well
+ * be meant as a vec3[4], they're wrapped by hand.
+ */
+macro_rules! impl_from_uniform_value {
+    ($($value_type:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$value_type> for UniformPackedParam {
+                fn from(value: $value_type) -> UniformPackedParam {
+                    UniformPackedParam::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+// Slices are copied into the matching array variant
+macro_rules! impl_from_uniform_array {
+    ($($value_type:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<Vec<$value_type>> for UniformPackedParam {
+                fn from(values: Vec<$value_type>) -> UniformPackedParam {
+                    UniformPackedParam::$variant(values)
+                }
+            }
+
+            impl From<&[$value_type]> for UniformPackedParam {
+                fn from(values: &[$value_type]) -> UniformPackedParam {
+                    UniformPackedParam::$variant(values.to_vec())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_uniform_value! {
+    f32 => Uniform1F,
+    Vec2 => Uniform2F,
+    Vec3 => Uniform3F,
+    Vec4 => Uniform4F,
+    i32 => Uniform1I,
+    IVec2 => Uniform2I,
+    IVec3 => Uniform3I,
+    IVec4 => Uniform4I,
+    u32 => Uniform1UI,
+    UVec2 => Uniform2UI,
+    UVec3 => Uniform3UI,
+    UVec4 => Uniform4UI,
+    bool => Uniform1B,
+    Mat2 => UniformMatrix2FV,
+    Mat3 => UniformMatrix3FV,
+    Mat4 => UniformMatrix4FV,
+}
+
+impl_from_uniform_array! {
+    f32 => Uniform1FArray,
+    Vec2 => Uniform2FArray,
+    Vec3 => Uniform3FArray,
+    Vec4 => Uniform4FArray,
+    i32 => Uniform1IArray,
+    IVec2 => Uniform2IArray,
+    IVec3 => Uniform3IArray,
+    IVec4 => Uniform4IArray,
+    u32 => Uniform1UIArray,
+    UVec2 => Uniform2UIArray,
+    UVec3 => Uniform3UIArray,
+    UVec4 => Uniform4UIArray,
+    bool => Uniform1BArray,
+    Mat2 => UniformMatrix2FVArray,
+    Mat3 => UniformMatrix3FVArray,
+    Mat4 => UniformMatrix4FVArray,
+}
+
+// GetUniformLocation gives -1 for names the program doesn't declare
+// or that got optimized out
+pub fn uniform_location(program_id: gl::types::GLuint, uniform_name: &str) -> gl::types::GLint {
+    unsafe {
+        gl::GetUniformLocation(
+            program_id,
+            CString::new(uniform_name.to_string()).unwrap().as_ptr(),
+        )
+    }
+}
+
+pub fn warn_inactive_uniform(program_id: gl::types::GLuint, uniform_name: &str) {
+    eprintln!(
+        "WARNING: program {} has no active uniform '{}', it is misspelled or optimized out",
+        program_id, uniform_name
+    );
 }
 
 impl UniformDescriptor {
     pub fn new(program_id: gl::types::GLuint, uniform_name: &str) -> UniformDescriptor {
-        let mut uniform_shader_handle = 0;
+        let uniform_shader_handle = uniform_location(program_id, uniform_name);
 
-        unsafe {
-            uniform_shader_handle = gl::GetUniformLocation(
-                program_id,
-                CString::new(uniform_name.to_string()).unwrap().as_ptr(),
-            );
+        if uniform_shader_handle == -1 {
+            warn_inactive_uniform(program_id, uniform_name);
         }
 
         UniformDescriptor {
+            name: uniform_name.to_string(),
             uniform_shader_handle: uniform_shader_handle,
         }
     }
 
-    pub fn update(&mut self, packed_param: UniformPackedParam) {
-        match packed_param {
-            UniformPackedParam::Uniform1F(param) => unsafe {
-                gl::Uniform1f(self.uniform_shader_handle, param.0);
-            },
-            UniformPackedParam::Uniform3F(param) => unsafe {
-                gl::Uniform3f(self.uniform_shader_handle, param.0, param.1, param.2);
-            },
-            UniformPackedParam::Uniform1I(param) => unsafe {
-                gl::Uniform1i(self.uniform_shader_handle, param.0);
-            },
-            UniformPackedParam::UniformMatrix4FV(param) => unsafe {
-                gl::UniformMatrix4fv(
-                    self.uniform_shader_handle,
-                    1,
-                    gl::FALSE,
-                    &param.0.to_cols_array()[0],
-                );
-            },
-        }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.uniform_shader_handle != -1
+    }
+
+    pub fn update(&mut self, packed_param: impl Into<UniformPackedParam>) {
+        packed_param.into().upload(self.uniform_shader_handle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrices_are_flattened_column_by_column() {
+        let mat2x3 = [Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 5.0, 6.0)];
+        assert_eq!(flatten_columns(&[mat2x3]), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let mat4x2s = [[Vec2::X, Vec2::Y, Vec2::ONE, Vec2::ZERO], [Vec2::splat(7.0); 4]];
+        let floats = flatten_columns(&mat4x2s);
+        assert_eq!(floats.len(), 16);
+        assert_eq!(&floats[..8], &[1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0]);
+        assert!(floats[8..].iter().all(|value| *value == 7.0));
+
+        let mat2 = Mat2::from_cols(Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0));
+        assert_eq!(flatten(&[mat2, mat2], Mat2::to_cols_array), vec![1.0, 2.0, 3.0, 4.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(flatten(&[IVec3::new(1, 2, 3)], IVec3::to_array), vec![1, 2, 3]);
     }
 }
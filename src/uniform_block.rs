@@ -0,0 +1,314 @@
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::mem::{offset_of, size_of};
+
+use glam::*;
+
+use crate::gl;
+use crate::light::{Light, LightKind};
+use crate::program::Program;
+
+// A #[repr(C)] struct laid out the way std140 lays out the GLSL block
+// called BLOCK_NAME. Every implementor should come with a const check of
+// its offsets, see CameraBlock below.
+pub trait Std140Block: Copy {
+    const BLOCK_NAME: &'static str;
+}
+
+// Uniform buffer object holding one T, shared by every program bound to
+// the same binding point
+pub struct UniformBuffer<T: Std140Block> {
+    buffer_id: gl::types::GLuint,
+    binding_point: gl::types::GLuint,
+    block: PhantomData<T>,
+}
+
+impl<T: Std140Block> UniformBuffer<T> {
+    pub fn new(binding_point: gl::types::GLuint) -> UniformBuffer<T> {
+        let mut buffer_id = 0;
+
+        unsafe {
+            gl::GenBuffers(1, &mut buffer_id);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, buffer_id);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                size_of::<T>() as gl::types::GLsizeiptr,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding_point, buffer_id);
+        }
+
+        UniformBuffer {
+            buffer_id: buffer_id,
+            binding_point: binding_point,
+            block: PhantomData,
+        }
+    }
+
+    pub fn update(&self, data: &T) {
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.buffer_id);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                size_of::<T>() as gl::types::GLsizeiptr,
+                data as *const T as *const gl::types::GLvoid,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+    }
+
+    // Points the program's block at our binding point. Returns false when the
+    // program doesn't use the block, and an error when the block GL sees has
+    // a different size than T.
+    pub fn bind_program(&self, program: &Program) -> Result<bool, String> {
+        let block_name = CString::new(T::BLOCK_NAME).unwrap();
+        let block_index = unsafe { gl::GetUniformBlockIndex(program.id, block_name.as_ptr()) };
+
+        if block_index == gl::INVALID_INDEX {
+            return Ok(false);
+        }
+
+        let mut block_size: gl::types::GLint = 0;
+        unsafe {
+            gl::GetActiveUniformBlockiv(
+                program.id,
+                block_index,
+                gl::UNIFORM_BLOCK_DATA_SIZE,
+                &mut block_size,
+            );
+        }
+
+        if block_size as usize != size_of::<T>() {
+            return Err(format!(
+                "uniform block '{}' of program {} is {} bytes, the Rust side is {}",
+                T::BLOCK_NAME,
+                program.id,
+                block_size,
+                size_of::<T>()
+            ));
+        }
+
+        unsafe {
+            gl::UniformBlockBinding(program.id, block_index, self.binding_point);
+        }
+
+        Ok(true)
+    }
+}
+
+impl<T: Std140Block> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.buffer_id);
+        }
+    }
+}
+
+// Binding point the Camera block of every program is bound to
+pub const CAMERA_BINDING_POINT: gl::types::GLuint = 0;
+
+/*
+ * layout(std140) uniform Camera
+ * {
+ *     mat4 projection;
+ *     mat4 look_at;
+ *     vec3 camera_position;
+ * };
+ */
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CameraBlock {
+    pub projection: Mat4,
+    pub look_at: Mat4,
+    pub camera_position: Vec3,
+    // vec3 takes the space of a vec4 at the end of a std140 block
+    pub _padding: f32,
+}
+
+impl Std140Block for CameraBlock {
+    const BLOCK_NAME: &'static str = "Camera";
+}
+
+impl CameraBlock {
+    pub fn new(projection: Mat4, look_at: Mat4, camera_position: Vec3) -> CameraBlock {
+        CameraBlock {
+            projection: projection,
+            look_at: look_at,
+            camera_position: camera_position,
+            _padding: 0.0,
+        }
+    }
+}
+
+// std140: a mat4 is four vec4 columns (16 byte aligned, 64 bytes),
+// a vec3 is 16 byte aligned and the block size rounds up to 16
+const _: () = {
+    assert!(offset_of!(CameraBlock, projection) == 0);
+    assert!(offset_of!(CameraBlock, look_at) == 64);
+    assert!(offset_of!(CameraBlock, camera_position) == 128);
+    assert!(size_of::<CameraBlock>() == 144);
+};
+
+// Binding point the Lights block of every program is bound to
+pub const LIGHTS_BINDING_POINT: gl::types::GLuint = 1;
+
+// Has to match MAX_LIGHTS in lit.frag
+pub const MAX_LIGHTS: usize = 8;
+
+// Values of the kind member, has to match lit.frag
+pub const DIRECTIONAL_LIGHT: i32 = 0;
+pub const POINT_LIGHT: i32 = 1;
+pub const SPOT_LIGHT: i32 = 2;
+
+/*
+ * struct Light
+ * {
+ *     vec3 position;
+ *     int kind;
+ *     vec3 direction;
+ *     float inner_cutoff;
+ *     vec3 ambient;
+ *     float outer_cutoff;
+ *     vec3 diffuse;
+ *     float constant;
+ *     vec3 specular;
+ *     float linear;
+ *     float quadratic;
+ * };
+ *
+ * Cutoffs are the cosines of the spot cone angles.
+ */
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LightData {
+    pub position: Vec3,
+    pub kind: i32,
+    pub direction: Vec3,
+    pub inner_cutoff: f32,
+    pub ambient: Vec3,
+    pub outer_cutoff: f32,
+    pub diffuse: Vec3,
+    pub constant: f32,
+    pub specular: Vec3,
+    pub linear: f32,
+    pub quadratic: f32,
+    // Structs in std140 round up to a multiple of 16
+    pub _padding: [f32; 3],
+}
+
+impl LightData {
+    // Black directional light, for the slots past light_count
+    pub fn unused() -> LightData {
+        LightData {
+            position: Vec3::ZERO,
+            kind: DIRECTIONAL_LIGHT,
+            direction: Vec3::NEG_Y,
+            inner_cutoff: 0.0,
+            ambient: Vec3::ZERO,
+            outer_cutoff: 0.0,
+            diffuse: Vec3::ZERO,
+            constant: 1.0,
+            specular: Vec3::ZERO,
+            linear: 0.0,
+            quadratic: 0.0,
+            _padding: [0.0; 3],
+        }
+    }
+
+    pub fn new(light: &Light) -> LightData {
+        let mut data = LightData {
+            ambient: light.ambient,
+            diffuse: light.diffuse,
+            specular: light.specular,
+            ..LightData::unused()
+        };
+
+        match light.kind {
+            LightKind::Directional { direction } => {
+                data.direction = direction.normalize();
+            }
+            LightKind::Point {
+                position,
+                attenuation,
+            } => {
+                data.kind = POINT_LIGHT;
+                data.position = position;
+                data.constant = attenuation.constant;
+                data.linear = attenuation.linear;
+                data.quadratic = attenuation.quadratic;
+            }
+            LightKind::Spot {
+                position,
+                direction,
+                inner_angle,
+                outer_angle,
+                attenuation,
+            } => {
+                data.kind = SPOT_LIGHT;
+                data.position = position;
+                data.direction = direction.normalize();
+                data.inner_cutoff = inner_angle.to_radians().cos();
+                data.outer_cutoff = outer_angle.to_radians().cos();
+                data.constant = attenuation.constant;
+                data.linear = attenuation.linear;
+                data.quadratic = attenuation.quadratic;
+            }
+        }
+
+        data
+    }
+}
+
+/*
+ * layout(std140) uniform Lights
+ * {
+ *     int light_count;
+ *     Light lights[MAX_LIGHTS];
+ * };
+ */
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LightsBlock {
+    pub light_count: i32,
+    // Arrays of structs start on a 16 byte boundary
+    pub _padding: [i32; 3],
+    pub lights: [LightData; MAX_LIGHTS],
+}
+
+impl Std140Block for LightsBlock {
+    const BLOCK_NAME: &'static str = "Lights";
+}
+
+impl LightsBlock {
+    // Lights past MAX_LIGHTS are left out
+    pub fn new(lights: &[Light]) -> LightsBlock {
+        let mut block = LightsBlock {
+            light_count: lights.len().min(MAX_LIGHTS) as i32,
+            _padding: [0; 3],
+            lights: [LightData::unused(); MAX_LIGHTS],
+        };
+
+        for (data, light) in block.lights.iter_mut().zip(lights.iter()) {
+            *data = LightData::new(light);
+        }
+
+        block
+    }
+}
+
+// std140: a vec3 followed by a scalar shares one 16 byte slot, so every
+// Light is 5 such slots plus the quadratic term rounded up to 16
+const _: () = {
+    assert!(offset_of!(LightData, kind) == 12);
+    assert!(offset_of!(LightData, direction) == 16);
+    assert!(offset_of!(LightData, ambient) == 32);
+    assert!(offset_of!(LightData, diffuse) == 48);
+    assert!(offset_of!(LightData, specular) == 64);
+    assert!(offset_of!(LightData, quadratic) == 80);
+    assert!(size_of::<LightData>() == 96);
+    assert!(offset_of!(LightsBlock, lights) == 16);
+    assert!(size_of::<LightsBlock>() == 16 + 96 * MAX_LIGHTS);
+};
@@ -1,10 +1,17 @@
+use std::collections::HashSet;
+use std::path::Path;
 use std::rc::Rc;
 
+use assimp::{Importer, Node, Scene};
+use assimp_sys::{aiGetMaterialTexture, aiGetMaterialTextureCount, AiReturn, AiString, AiTextureType};
+
 use crate::buffer::*;
 use crate::gl;
+use crate::light::Material;
+use crate::stats;
 use crate::program::Program;
 use crate::texture::TextureDescriptor;
-use crate::uniform::UniformDescriptor;
+use crate::uniform::{UniformDescriptor, UniformPackedParam};
 use glam::*;
 
 // Each model can have several sub-models/shapes
@@ -12,20 +19,253 @@ use glam::*;
 pub struct Model {
     pub meshes: Vec<Mesh>,
     pub program: Option<Rc<Program>>,
+    pub nodes: Vec<ModelNode>,
+}
+
+// Transform hierarchy of a loaded model, the root nodes live in Model::nodes
+// and mesh indices point into Model::meshes
+pub struct ModelNode {
+    pub name: String,
+    pub transform: Mat4,
+    pub meshes: Vec<usize>,
+    pub children: Vec<ModelNode>,
+}
+
+/*
+ * A texture stored inside the model file. Compressed ones (PNG, JPEG, ...)
+ * have a height of 0 and width bytes of data, the others are width x height
+ * BGRA texels, top row first.
+ */
+fn embedded_texture(
+    scene: &Scene,
+    embedded_idx: &str,
+    program_id: gl::types::GLuint,
+    sampler_name: &str,
+) -> Result<TextureDescriptor, String> {
+    let texture = match embedded_idx.parse::<usize>().ok().and_then(|idx| scene.texture_iter().nth(idx)) {
+        Some(texture) => texture,
+        None => {
+            return Err(format!(
+                "the file has {} embedded textures, no *{}",
+                scene.num_textures(),
+                embedded_idx
+            ));
+        }
+    };
+
+    if texture.height == 0 {
+        let bytes = unsafe { std::slice::from_raw_parts(texture.data as *const u8, texture.width as usize) };
+        return TextureDescriptor::from_memory(program_id, sampler_name, bytes);
+    }
+
+    let (width, height) = (texture.width as usize, texture.height as usize);
+    let texels = unsafe { std::slice::from_raw_parts(texture.data, width * height) };
+
+    // Bottom row first, like files are loaded
+    let mut pixels: Vec<u8> = Vec::with_capacity(width * height * 4);
+    for row in texels.chunks_exact(width).rev() {
+        for texel in row.iter() {
+            pixels.extend_from_slice(&[texel.r, texel.g, texel.b, texel.a]);
+        }
+    }
+
+    Ok(TextureDescriptor::from_pixels(
+        program_id,
+        sampler_name,
+        width as i32,
+        height as i32,
+        gl::RGBA,
+        &pixels,
+    ))
+}
+
+// Assimp matrices are row major, glam's column major
+fn assimp_node(node: &Node, first_mesh: usize) -> ModelNode {
+    let m = node.transformation();
+    let transform = Mat4::from_cols_array(&[
+        m.a1, m.a2, m.a3, m.a4, m.b1, m.b2, m.b3, m.b4, m.c1, m.c2, m.c3, m.c4, m.d1, m.d2, m.d3, m.d4,
+    ])
+    .transpose();
+
+    ModelNode {
+        name: node.name().to_string(),
+        transform: transform,
+        meshes: node.meshes().iter().map(|mesh_idx| first_mesh + *mesh_idx as usize).collect(),
+        children: node.child_iter().map(|child| assimp_node(&child, first_mesh)).collect(),
+    }
 }
 
 // Models can be made up of multiple meshes
 impl Model {
     pub fn new() -> Model {
-        Model { meshes: Vec::new(), program: None }
+        Model { meshes: Vec::new(), program: None, nodes: Vec::new() }
     }
 
     pub fn add_mesh(&mut self, mesh: Mesh) {
         self.meshes.push(mesh);
     }
 
-    pub fn parse_mesh_from_file() {
+    // Loads every mesh found in an OBJ/FBX/glTF/Collada/... file through assimp.
+    // Vertices are laid out as 3 position, 3 normal and 2 texture components,
+    // diffuse and specular textures of each mesh's material are bound to the
+    // texture_diffuseN/texture_specularN samplers of the given program.
+    // The file's node hierarchy is added to nodes, see mesh_transforms().
+    pub fn parse_mesh_from_file(&mut self, path: &str, program_id: gl::types::GLuint) -> Result<(), String> {
+        let mut importer = Importer::new();
+        importer.triangulate(true);
+        importer.join_identical_vertices(true);
+        importer.generate_normals(|normals| {
+            normals.enable = true;
+            normals.smooth = true;
+        });
+
+        let scene = match importer.read_file(path) {
+            Ok(scene) => scene,
+            Err(e) => {
+                return Err(format!("Failed to import {}: {}", path, e));
+            }
+        };
+
+        if scene.is_incomplete() {
+            return Err(format!("Scene in {} is incomplete", path));
+        }
+
+        let model_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        let materials: Vec<_> = scene.material_iter().collect();
+        // Meshes of the file are added after any the model already has
+        let first_mesh = self.meshes.len();
+
+        for assimp_mesh in scene.mesh_iter() {
+            let has_uvs = assimp_mesh.has_texture_coords(0);
+            let mut vertices: Vec<f32> =
+                Vec::with_capacity(assimp_mesh.num_vertices() as usize * (3 + 3 + 2));
+
+            for idx in 0..assimp_mesh.num_vertices() {
+                let position = assimp_mesh.get_vertex(idx).unwrap();
+                vertices.extend_from_slice(&[position.x, position.y, position.z]);
+
+                match assimp_mesh.get_normal(idx) {
+                    Some(normal) if assimp_mesh.has_normals() => {
+                        vertices.extend_from_slice(&[normal.x, normal.y, normal.z]);
+                    }
+                    _ => {
+                        vertices.extend_from_slice(&[0.0, 0.0, 0.0]);
+                    }
+                }
 
+                if has_uvs {
+                    let uv = assimp_mesh.get_texture_coord(0, idx).unwrap();
+                    vertices.extend_from_slice(&[uv.x, uv.y]);
+                } else {
+                    vertices.extend_from_slice(&[0.0, 0.0]);
+                }
+            }
+
+            let mut indices: Vec<u32> = Vec::with_capacity(assimp_mesh.num_faces() as usize * 3);
+            for face in assimp_mesh.face_iter() {
+                // Points and lines survive triangulation, we only draw triangles
+                if face.num_indices != 3 {
+                    continue;
+                }
+                indices.extend_from_slice(&[face[0], face[1], face[2]]);
+            }
+
+            let attributes = AttributesDescriptor {
+                component_groups: 3,
+                component_nums: vec![3, 3, 2],
+                component_types: vec![gl::FLOAT, gl::FLOAT, gl::FLOAT],
+                component_offsets: vec![0, 3, 6],
+                component_strides: vec![8, 8, 8],
+            };
+
+            let mut mesh = Mesh::new(vertices, indices, attributes);
+
+            if let Some(material) = materials.get(assimp_mesh.material_index as usize) {
+                for (texture_type, sampler_prefix) in [
+                    (AiTextureType::Diffuse, "texture_diffuse"),
+                    (AiTextureType::Specular, "texture_specular"),
+                ] {
+                    let texture_count =
+                        unsafe { aiGetMaterialTextureCount(material.to_raw(), texture_type) };
+
+                    for texture_idx in 0..texture_count {
+                        let mut texture_path = AiString::default();
+                        let ret = unsafe {
+                            aiGetMaterialTexture(
+                                material.to_raw(),
+                                texture_type,
+                                texture_idx,
+                                &mut texture_path,
+                                std::ptr::null(),
+                                std::ptr::null_mut(),
+                                std::ptr::null_mut(),
+                                std::ptr::null_mut(),
+                                std::ptr::null_mut(),
+                                std::ptr::null_mut(),
+                            )
+                        };
+                        if ret != AiReturn::Success {
+                            eprintln!("Failed to get texture {} of material in {}", texture_idx, path);
+                            continue;
+                        }
+
+                        let relative_path = String::from_utf8_lossy(
+                            &texture_path.data[..texture_path.length],
+                        )
+                        .into_owned();
+                        let sampler_name = format!("{}{}", sampler_prefix, texture_idx + 1);
+
+                        // Textures embedded in the file are called * and their index
+                        let texture = match relative_path.strip_prefix('*') {
+                            Some(embedded_idx) => embedded_texture(&scene, embedded_idx, program_id, &sampler_name)
+                                .map_err(|e| format!("Failed to load texture {} of {}: {}", relative_path, path, e))?,
+                            None => {
+                                let texture_file = model_dir.join(relative_path.replace('\\', "/"));
+                                let texture_file = match texture_file.to_str() {
+                                    Some(texture_file) => texture_file,
+                                    None => {
+                                        return Err(format!(
+                                            "Texture path {} in {} isn't valid UTF-8",
+                                            texture_file.display(),
+                                            path
+                                        ));
+                                    }
+                                };
+                                TextureDescriptor::new_detect_format(program_id, &sampler_name, texture_file)?
+                            }
+                        };
+
+                        mesh.add_texture(texture);
+                    }
+                }
+            }
+
+            self.add_mesh(mesh);
+        }
+
+        self.nodes.push(assimp_node(&scene.root_node(), first_mesh));
+
+        Ok(())
+    }
+
+    // Transform of every mesh relative to the model, following the node
+    // hierarchy of loaded files. Meshes no node refers to keep the identity.
+    pub fn mesh_transforms(&self) -> Vec<Mat4> {
+        let mut transforms = vec![Mat4::IDENTITY; self.meshes.len()];
+        let mut pending: Vec<(&ModelNode, Mat4)> =
+            self.nodes.iter().map(|node| (node, Mat4::IDENTITY)).collect();
+
+        while let Some((node, parent_transform)) = pending.pop() {
+            let transform = parent_transform * node.transform;
+            for mesh_idx in node.meshes.iter() {
+                transforms[*mesh_idx] = transform;
+            }
+            for child in node.children.iter() {
+                pending.push((child, transform));
+            }
+        }
+
+        transforms
     }
 
     pub fn attach_program(&mut self, program: Rc<Program>) {
@@ -42,6 +282,7 @@ impl Model {
                     std::ptr::null(),
                 );
             }
+            stats::count_draw_call();
         }
     }
 
@@ -58,6 +299,9 @@ pub struct Mesh {
     pub face_indices: Rc<Vec<u32>>,
     pub textures: Vec<TextureDescriptor>,
     pub uniforms: Vec<UniformDescriptor>,
+    pub attributes: AttributesDescriptor,
+    pub material: Material,
+    missing_uniforms: HashSet<String>,
     buffer: Rc<BufferDescriptor>,
     vao: VaoDescriptor,
     ebo: Rc<EboDescriptor>,
@@ -67,7 +311,7 @@ impl Mesh {
     pub fn new(vertices: Vec<f32>, indices: Vec<u32>, attributes: AttributesDescriptor) -> Mesh {
         let indices_ref = Rc::new(indices);
         let buffer = Rc::new(BufferDescriptor::new(&vertices));
-        let mut vao = VaoDescriptor::new(attributes, Rc::clone(&buffer));
+        let mut vao = VaoDescriptor::new(attributes.clone(), Rc::clone(&buffer));
         let ebo = Rc::new(EboDescriptor::new(Rc::clone(&indices_ref)));
         vao.attach_ebo(Rc::clone(&ebo));
 
@@ -77,6 +321,9 @@ impl Mesh {
             face_indices: indices_ref,
             textures: Vec::new(),
             uniforms: Vec::new(),
+            attributes: attributes,
+            material: Material::default(),
+            missing_uniforms: HashSet::new(),
             vao: vao,
             ebo: ebo,
         }
@@ -90,6 +337,19 @@ impl Mesh {
         self.uniforms.push(uniform);
     }
 
+    // Updates one of the uniforms added with add_uniform(), asking for one
+    // that was never added is reported once per name
+    pub fn set_uniform(&mut self, name: &str, value: impl Into<UniformPackedParam>) {
+        match self.uniforms.iter_mut().find(|uniform| uniform.name() == name) {
+            Some(uniform) => uniform.update(value),
+            None => {
+                if self.missing_uniforms.insert(name.to_string()) {
+                    eprintln!("WARNING: mesh has no uniform named '{}'", name);
+                }
+            }
+        }
+    }
+
     pub fn add_texture(&mut self, texture: TextureDescriptor) {
         self.textures.push(texture);
     }
@@ -103,6 +363,7 @@ impl Mesh {
                 std::ptr::null(),
             );
         }
+        stats::count_draw_call();
     }
 }
 
@@ -110,6 +371,7 @@ impl Mesh {
  * component_groups: Groups of components vertices are made out of
  * component_nums:   Number of components for each group, i.e. 3 position, 3 color, 2 texture,
  */
+#[derive(Clone)]
 pub struct AttributesDescriptor {
     pub component_groups: gl::types::GLuint,
     pub component_nums: Vec<gl::types::GLint>,